@@ -1,3 +1,4 @@
+use crate::error::XorSlpError;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::ops::{Index, IndexMut};
@@ -108,13 +109,13 @@ impl BitMatrix {
         }
     }
 
-    pub fn build_from_file(file: File) -> Option<Self> {
+    pub fn build_from_file(file: File) -> Result<Self, XorSlpError> {
         let mut inner: Vec<Vec<bool>> = Vec::new();
         let reader = BufReader::new(file);
 
         for line in reader.lines() {
             // read one line
-            let line: String = line.expect("there is an invalid line in the input file");
+            let line: String = line?;
 
             // a line which starts with # is a comment and skipped
             if line.as_str().starts_with('#') {
@@ -131,8 +132,10 @@ impl BitMatrix {
                 } else if c == '1' {
                     bitvec[i] = true;
                 } else {
-                    println!("there is an invalid character `{}` in the input file", c);
-                    return None;
+                    return Err(XorSlpError::MalformedInput(format!(
+                        "there is an invalid character `{}` in the input file",
+                        c
+                    )));
                 }
             }
 
@@ -140,12 +143,21 @@ impl BitMatrix {
         }
 
         // check the input file forms an bitmatrix
-        let l = inner[0].len();
-        for v in &inner[1..] {
-            assert_eq!(l, v.len());
+        let l = match inner.first() {
+            Some(v) => v.len(),
+            None => {
+                return Err(XorSlpError::MalformedInput(
+                    "the input file has no rows".to_owned(),
+                ))
+            }
+        };
+        if inner.iter().any(|v| v.len() != l) {
+            return Err(XorSlpError::MalformedInput(
+                "the rows of the input file have different lengths".to_owned(),
+            ));
         }
 
-        Some(Self { inner })
+        Ok(Self { inner })
     }
 
     pub fn mul(&self, right: &BitMatrix) -> BitMatrix {
@@ -171,21 +183,30 @@ impl BitMatrix {
 }
 
 pub fn u8_to_colvec(u: u8) -> BitMatrix {
-    let mut bm = BitMatrix::new(8, 1);
+    bits_to_colvec(u as u32, 8)
+}
+
+pub fn colvec_to_u8(m: &BitMatrix) -> u8 {
+    colvec_to_bits(m, 8) as u8
+}
 
-    for i in 0..8 {
-        bm[i][0] = (u >> (7 - i)) & 1 == 1;
+/// The `w`-bit column vector of `u`, whose most significant bit is the first row.
+pub fn bits_to_colvec(u: u32, w: usize) -> BitMatrix {
+    let mut bm = BitMatrix::new(w, 1);
+
+    for i in 0..w {
+        bm[i][0] = (u >> (w - 1 - i)) & 1 == 1;
     }
 
     bm
 }
 
-pub fn colvec_to_u8(m: &BitMatrix) -> u8 {
-    let mut u = 0u8;
+pub fn colvec_to_bits(m: &BitMatrix, w: usize) -> u32 {
+    let mut u = 0u32;
 
-    for i in 0..8 {
+    for i in 0..w {
         if m[i][0] {
-            u |= 1 << (7 - i);
+            u |= 1 << (w - 1 - i);
         }
     }
 
@@ -202,4 +223,37 @@ mod tests {
             assert_eq!(colvec_to_u8(&u8_to_colvec(u)), u);
         }
     }
+
+    #[test]
+    fn build_from_file_test() {
+        use std::io::Write;
+
+        let parse = |name: &str, content: &str| {
+            let path = std::env::temp_dir().join(format!("xorslp_ec_{}.txt", name));
+            std::fs::File::create(&path)
+                .unwrap()
+                .write_all(content.as_bytes())
+                .unwrap();
+            let result = BitMatrix::build_from_file(File::open(&path).unwrap());
+            std::fs::remove_file(&path).unwrap();
+            result
+        };
+
+        let m = parse("valid", "# comment\n0101\n1100\n").unwrap();
+        assert_eq!(m.height(), 2);
+        assert_eq!(m.width(), 4);
+
+        assert!(matches!(
+            parse("ragged", "0101\n110\n"),
+            Err(XorSlpError::MalformedInput(_))
+        ));
+        assert!(matches!(
+            parse("invalid", "0121\n"),
+            Err(XorSlpError::MalformedInput(_))
+        ));
+        assert!(matches!(
+            parse("empty", "# nothing\n"),
+            Err(XorSlpError::MalformedInput(_))
+        ));
+    }
 }
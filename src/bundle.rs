@@ -0,0 +1,433 @@
+use crate::error::XorSlpError;
+use crate::pipeline::Options;
+use crate::program_file::{generator_hash, ProgramFile};
+use crate::*;
+use itertools::Itertools;
+use std::fs::File;
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/*
+ * The decoding programs of all the erasure patterns of a code in one file.
+ * All the integers are little endian:
+ *
+ *   magic "XSLB", version: u32,
+ *   nr_data_block: u32, nr_parity_block: u32, generator hash: u64, #entries: u64,
+ *   index: (offset: u64, len: u64) * #entries,
+ *   the entries, each of which is a `ProgramFile` with `wanted = erased`
+ *
+ * The patterns with 1 erased block come first, then those with 2, and so on up to nr_parity_block.
+ * Within the same size, a sorted pattern c_0 < c_1 < ... < c_{e-1} is at the colexicographic rank
+ * C(c_0, 1) + C(c_1, 2) + ... + C(c_{e-1}, e), so that the entry is found without searching.
+ */
+const MAGIC: &[u8; 4] = b"XSLB";
+pub const BUNDLE_VERSION: u32 = 1;
+const HEADER_LEN: usize = 4 + 4 + 4 + 4 + 8 + 8;
+
+// binomial[n][r] = C(n, r) for r <= max_r, or None if some of them overflows
+fn binomials(n: usize, max_r: usize) -> Option<Vec<Vec<usize>>> {
+    let mut table = vec![vec![0usize; max_r + 1]; n + 1];
+    for i in 0..=n {
+        table[i][0] = 1;
+        for r in 1..=std::cmp::min(i, max_r) {
+            table[i][r] = table[i - 1][r - 1].checked_add(table[i - 1][r])?;
+        }
+    }
+    Some(table)
+}
+
+struct Layout {
+    nr_shards: usize,
+    nr_parity: usize,
+    nr_entries: usize,
+    binomial: Vec<Vec<usize>>,
+}
+
+impl Layout {
+    // None if the number of the entries or the length of the index overflows
+    fn new(parameter: Parameter) -> Option<Self> {
+        let nr_shards = parameter.nr_data_block + parameter.nr_parity_block;
+        let binomial = binomials(nr_shards, parameter.nr_parity_block)?;
+        let nr_entries = (1..=parameter.nr_parity_block)
+            .try_fold(0usize, |sum, e| sum.checked_add(binomial[nr_shards][e]))?;
+        nr_entries.checked_mul(16)?.checked_add(HEADER_LEN)?;
+
+        Some(Layout {
+            nr_shards,
+            nr_parity: parameter.nr_parity_block,
+            nr_entries,
+            binomial,
+        })
+    }
+
+    fn nr_entries(&self) -> usize {
+        self.nr_entries
+    }
+
+    fn slot(&self, erased: &[usize]) -> Result<usize, XorSlpError> {
+        if erased.is_empty() || erased.len() > self.nr_parity {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "{} blocks are erased; 1 to {} are supported",
+                erased.len(),
+                self.nr_parity
+            )));
+        }
+        if !erased.windows(2).all(|w| w[0] < w[1]) || erased[erased.len() - 1] >= self.nr_shards {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "erased blocks {:?} are not sorted blocks",
+                erased
+            )));
+        }
+
+        let offset: usize = (1..erased.len())
+            .map(|e| self.binomial[self.nr_shards][e])
+            .sum();
+        let rank: usize = erased
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| self.binomial[c][i + 1])
+            .sum();
+        Ok(offset + rank)
+    }
+
+    // the patterns in the order of their slots
+    fn patterns(&self) -> Vec<Vec<usize>> {
+        let mut patterns = vec![Vec::new(); self.nr_entries()];
+        for e in 1..=self.nr_parity {
+            for erased in (0..self.nr_shards).combinations(e) {
+                let slot = self.slot(&erased).unwrap();
+                patterns[slot] = erased;
+            }
+        }
+        patterns
+    }
+}
+
+/// Optimize the programs of all the erasure patterns of at most `nr_parity_block` blocks
+/// with `nr_threads` worker threads, and write them as a bundle.
+pub fn write_bundle<W: Write>(
+    writer: &mut W,
+    parameter: Parameter,
+    options: &Options,
+    nr_threads: usize,
+) -> Result<(), XorSlpError> {
+    parameter.check()?;
+    let layout = Layout::new(parameter).ok_or_else(|| {
+        XorSlpError::InvalidParameter(format!("{:?} has too many erasure patterns", parameter))
+    })?;
+    let patterns = layout.patterns();
+
+    let next = AtomicUsize::new(0);
+    // the serialized programs, filled by the workers
+    type Entry = Option<Result<Vec<u8>, XorSlpError>>;
+    let entries: Mutex<Vec<Entry>> = Mutex::new(vec![None; patterns.len()]);
+    std::thread::scope(|s| {
+        for _ in 0..std::cmp::max(nr_threads, 1) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= patterns.len() {
+                    break;
+                }
+                let erased = patterns[i].clone();
+                let entry =
+                    ProgramFile::new(parameter, erased.clone(), erased, options).and_then(|file| {
+                        let mut bytes = Vec::new();
+                        file.write(&mut bytes)?;
+                        Ok(bytes)
+                    });
+                entries.lock().unwrap()[i] = Some(entry);
+            });
+        }
+    });
+    let entries: Vec<Vec<u8>> = entries
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.unwrap())
+        .collect::<Result<_, _>>()?;
+
+    let generator = vandermonde::isa_rsv(parameter.nr_data_block, parameter.nr_parity_block);
+    let mut header: Vec<u8> = Vec::new();
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&BUNDLE_VERSION.to_le_bytes());
+    header.extend_from_slice(&(parameter.nr_data_block as u32).to_le_bytes());
+    header.extend_from_slice(&(parameter.nr_parity_block as u32).to_le_bytes());
+    header.extend_from_slice(&generator_hash(&generator).to_le_bytes());
+    header.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+
+    let mut offset = HEADER_LEN + entries.len() * 16;
+    for entry in &entries {
+        header.extend_from_slice(&(offset as u64).to_le_bytes());
+        header.extend_from_slice(&(entry.len() as u64).to_le_bytes());
+        offset += entry.len();
+    }
+
+    writer.write_all(&header)?;
+    for entry in &entries {
+        writer.write_all(entry)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// a read-only memory mapping of a whole file
+struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+// the mapping is never written
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    fn new(file: &File) -> Result<Self, XorSlpError> {
+        let len = file.metadata()?.len() as usize;
+        if len == 0 {
+            return Err(XorSlpError::MalformedInput("empty bundle".to_owned()));
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(Mmap { ptr, len })
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr, self.len) };
+    }
+}
+
+/// A bundle written by `write_bundle`, memory-mapped.
+pub struct Bundle {
+    parameter: Parameter,
+    layout: Layout,
+    map: Mmap,
+}
+
+impl Bundle {
+    /// Map the bundle at `path` and check its header and index.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, XorSlpError> {
+        let map = Mmap::new(&File::open(path)?)?;
+        let bytes = map.as_slice();
+        let malformed = |msg: &str| Err(XorSlpError::MalformedInput(msg.to_owned()));
+
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return malformed("not a bundle");
+        }
+        if u32_at(bytes, 4) != BUNDLE_VERSION {
+            return malformed("the bundle version is not supported");
+        }
+        let parameter = Parameter {
+            nr_data_block: u32_at(bytes, 8) as usize,
+            nr_parity_block: u32_at(bytes, 12) as usize,
+        };
+        parameter.check()?;
+
+        let generator = vandermonde::isa_rsv(parameter.nr_data_block, parameter.nr_parity_block);
+        if u64_at(bytes, 16) != generator_hash(&generator) {
+            return malformed("the bundle is for another generator matrix");
+        }
+
+        let layout = match Layout::new(parameter) {
+            Some(layout) => layout,
+            None => return malformed("the bundle has too many entries"),
+        };
+        let nr_entries = layout.nr_entries();
+        if u64_at(bytes, 24) != nr_entries as u64 || bytes.len() < HEADER_LEN + nr_entries * 16 {
+            return malformed("the index is broken");
+        }
+        for slot in 0..nr_entries {
+            let offset = u64_at(bytes, HEADER_LEN + slot * 16);
+            let len = u64_at(bytes, HEADER_LEN + slot * 16 + 8);
+            if offset
+                .checked_add(len)
+                .is_none_or(|end| end > bytes.len() as u64)
+            {
+                return malformed("an entry is out of the bundle");
+            }
+        }
+
+        Ok(Bundle {
+            parameter,
+            layout,
+            map,
+        })
+    }
+
+    pub fn parameter(&self) -> Parameter {
+        self.parameter
+    }
+
+    pub fn nr_entries(&self) -> usize {
+        self.layout.nr_entries()
+    }
+
+    /// The program for the sorted erasure set `erased`, validated as in `ProgramFile::read`.
+    pub fn lookup(&self, erased: &[usize]) -> Result<ProgramFile, XorSlpError> {
+        let slot = self.layout.slot(erased)?;
+        let bytes = self.map.as_slice();
+        let offset = u64_at(bytes, HEADER_LEN + slot * 16) as usize;
+        let len = u64_at(bytes, HEADER_LEN + slot * 16 + 8) as usize;
+
+        let file = ProgramFile::read(&mut &bytes[offset..offset + len])?;
+        if file.parameter() != self.parameter || file.erased() != erased || file.wanted() != erased
+        {
+            return Err(XorSlpError::MalformedInput(format!(
+                "the entry for {:?} is for another pattern",
+                erased
+            )));
+        }
+        Ok(file)
+    }
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    let mut b = [0u8; 4];
+    b.copy_from_slice(&bytes[at..at + 4]);
+    u32::from_le_bytes(b)
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    let mut b = [0u8; 8];
+    b.copy_from_slice(&bytes[at..at + 8]);
+    u64::from_le_bytes(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::Decoder;
+    use std::sync::Arc;
+
+    #[test]
+    fn layout_test() {
+        let layout = Layout::new(Parameter {
+            nr_data_block: 10,
+            nr_parity_block: 4,
+        })
+        .unwrap();
+        assert_eq!(layout.nr_entries(), 14 + 91 + 364 + 1001);
+
+        // every slot is taken by exactly one pattern
+        let patterns = layout.patterns();
+        assert!(patterns.iter().all(|p| !p.is_empty()));
+        for (slot, erased) in patterns.iter().enumerate() {
+            assert_eq!(layout.slot(erased), Ok(slot));
+        }
+
+        assert!(layout.slot(&[]).is_err());
+        assert!(layout.slot(&[0, 1, 2, 3, 4]).is_err());
+        assert!(layout.slot(&[3, 1]).is_err());
+        assert!(layout.slot(&[14]).is_err());
+
+        // only C(n, r) for r <= nr_parity_block are needed
+        let layout = Layout::new(Parameter {
+            nr_data_block: 64,
+            nr_parity_block: 4,
+        })
+        .unwrap();
+        assert_eq!(layout.nr_entries(), 68 + 2278 + 50116 + 814385);
+
+        // C(256, 128) does not fit in usize
+        let parameter = Parameter {
+            nr_data_block: 128,
+            nr_parity_block: 128,
+        };
+        assert!(Layout::new(parameter).is_none());
+        assert!(matches!(
+            write_bundle(&mut Vec::new(), parameter, &Options::default(), 1),
+            Err(XorSlpError::InvalidParameter(_))
+        ));
+    }
+
+    #[test]
+    fn bundle_test() {
+        let parameter = Parameter {
+            nr_data_block: 3,
+            nr_parity_block: 2,
+        };
+        let options = Options::default();
+        let path = std::env::temp_dir().join(format!("xorslp_bundle_{}", std::process::id()));
+
+        let mut bytes = Vec::new();
+        write_bundle(&mut bytes, parameter, &options, 2).unwrap();
+        std::fs::write(&path, &bytes).unwrap();
+        let bundle = Arc::new(Bundle::open(&path).unwrap());
+        assert_eq!(bundle.nr_entries(), 5 + 10);
+
+        let decoder = Decoder::new(parameter, options)
+            .unwrap()
+            .with_bundle(bundle.clone())
+            .unwrap();
+        let len = 8 * 64 * 2;
+        let mut original = vec![0u8; len * 5];
+        fill_by_random(&mut original);
+        let encoder = encoder::Encoder::new(parameter, options).unwrap();
+        {
+            let (data, parity) = original.split_at_mut(len * 3);
+            let data_shards: Vec<&[u8]> = data.chunks(len).collect();
+            let mut parity_shards: Vec<&mut [u8]> = parity.chunks_mut(len).collect();
+            encoder.encode(&data_shards, &mut parity_shards).unwrap();
+        }
+
+        for erased in [vec![0], vec![4], vec![1, 3], vec![3, 4]] {
+            assert_eq!(bundle.lookup(&erased).unwrap().erased(), &erased[..]);
+
+            let mut shards = original.clone();
+            for &i in &erased {
+                shards[len * i..len * (i + 1)].fill(0);
+            }
+            let present: Vec<bool> = (0..5).map(|i| !erased.contains(&i)).collect();
+            let mut chunks: Vec<&mut [u8]> = shards.chunks_mut(len).collect();
+            decoder.reconstruct(&mut chunks, &present).unwrap();
+            assert!(shards == original);
+        }
+
+        // a broken entry is rejected on lookup
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+        let broken = Bundle::open(&path).unwrap();
+        assert!(broken.lookup(&[3, 4]).is_err());
+        assert!(broken.lookup(&[0]).is_ok());
+
+        bytes.truncate(HEADER_LEN + 8);
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            Bundle::open(&path),
+            Err(XorSlpError::MalformedInput(_))
+        ));
+
+        // a header for too many erasure patterns
+        let generator = vandermonde::isa_rsv(128, 128);
+        bytes[8..12].copy_from_slice(&128u32.to_le_bytes());
+        bytes[12..16].copy_from_slice(&128u32.to_le_bytes());
+        bytes[16..24].copy_from_slice(&generator_hash(&generator).to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            Bundle::open(&path),
+            Err(XorSlpError::MalformedInput(_))
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
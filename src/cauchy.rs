@@ -0,0 +1,266 @@
+use crate::error::XorSlpError;
+use crate::field::*;
+use crate::fin_field::*;
+use crate::for_benchmark;
+use crate::matrix::*;
+use crate::rsv_bitmatrix::{self, BIT_GF_2_8_IMPL};
+use crate::slp::SLP;
+use crate::Parameter;
+use clap::arg_enum;
+
+/*
+ * cauchy(n, p, xs=[x_1, ..., x_p], ys=[y_1, ..., y_n])
+ * is the systematic (n + p) x n matrix
+ * (            I_n            )
+ * ( 1/(x_1 + y_1) ... 1/(x_1 + y_n) )
+ * (                ...              )
+ * ( 1/(x_p + y_1) ... 1/(x_p + y_n) )
+ *
+ * Every square submatrix of a Cauchy matrix is invertible,
+ * and so is that of the parity rows scaled by nonzero row and column factors.
+ * Hence the generators are MDS for any distinct x_1, ..., x_p, y_1, ..., y_n.
+ */
+pub fn cauchy(n: usize, p: usize, xs: &[GF_2_8], ys: &[GF_2_8]) -> Option<Matrix<GF_2_8>> {
+    if xs.len() != p || ys.len() != n {
+        return None;
+    }
+
+    let elems: Vec<GF_2_8> = xs.iter().chain(ys.iter()).cloned().collect();
+    for i in 0..elems.len() {
+        if elems[i + 1..].contains(&elems[i]) {
+            return None;
+        }
+    }
+
+    let mut m = Matrix::new(MatrixSize {
+        height: n + p,
+        width: n,
+    });
+
+    for i in 0..n {
+        m[i][i] = GF_2_8::ONE;
+    }
+
+    for i in 0..p {
+        for j in 0..n {
+            m[n + i][j] = (xs[i] + ys[j]).mul_inv();
+        }
+    }
+
+    Some(m)
+}
+
+/// Multiply the parity row `i` by `row_scales[i]` and the column `j` of the parity rows by `col_scales[j]`.
+/// The scales must be nonzero.
+pub fn scale(
+    m: &Matrix<GF_2_8>,
+    row_scales: &[GF_2_8],
+    col_scales: &[GF_2_8],
+) -> Option<Matrix<GF_2_8>> {
+    let n = m.width();
+    if row_scales.len() + n != m.height()
+        || col_scales.len() != n
+        || row_scales
+            .iter()
+            .chain(col_scales)
+            .any(|s| *s == GF_2_8::ZERO)
+    {
+        return None;
+    }
+
+    let mut m = m.clone();
+    for (i, r) in row_scales.iter().enumerate() {
+        for (j, c) in col_scales.iter().enumerate() {
+            m[n + i][j] = m[n + i][j] * *r * *c;
+        }
+    }
+    Some(m)
+}
+
+fn ones(e: GF_2_8) -> usize {
+    BIT_GF_2_8_IMPL
+        .to_nested_array(e.into())
+        .iter()
+        .map(|v| v.count_ones() as usize)
+        .sum()
+}
+
+/// The number of ones in the bitmatrix of `m`, i.e., the XORs of the unoptimized program plus the rows.
+pub fn nr_ones(m: &Matrix<GF_2_8>) -> usize {
+    m.iter()
+        .flat_map(|row| row.as_vec().iter())
+        .map(|e| ones(*e))
+        .sum()
+}
+
+/// The number of XORs of the program of `m` compressed by XorRePair.
+pub fn nr_xors(m: &Matrix<GF_2_8>) -> Result<usize, XorSlpError> {
+    let bitmatrix = rsv_bitmatrix::matrix_to_bitmatrix(m);
+    let slp = SLP::build_from_bitmatrix_not_depending_variables(&bitmatrix);
+    let shrinked = for_benchmark::shrink(&slp);
+    if shrinked.is_empty() {
+        return Ok(0);
+    }
+    let compressed = for_benchmark::xor_repair(&shrinked);
+    Ok(for_benchmark::graph_analyze(&shrinked, &compressed)?
+        .0
+        .nr_xors)
+}
+
+arg_enum! {
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum CauchyObjective {
+        Ones, // of the bitmatrix
+        Xors, // after XorRePair
+    }
+}
+
+/*
+ * The good Cauchy search:
+ *
+ * For given X and Y, the scaling follows Plank and Xu (NCA 2006):
+ * for every parity row r, the columns are scaled so that the row r consists of ones,
+ * and then every other parity row is scaled by the nonzero factor minimizing its ones.
+ * The best of the p scalings is the one of X and Y.
+ *
+ * X and Y are found by a local search from X = {0, ..., p-1}, Y = {p, ..., p+n-1}:
+ * an element of X or Y is replaced by an unused one while this decreases the ones.
+ * For the XOR objective, XorRePair is too costly to run for every candidate,
+ * so the XOR counts are compared only among the last POOL_SIZE improvements of the search.
+ */
+const POOL_SIZE: usize = 8;
+
+// the best scaling of cauchy(xs, ys) and its number of ones
+fn best_scaling(n: usize, p: usize, xs: &[GF_2_8], ys: &[GF_2_8]) -> (Matrix<GF_2_8>, usize) {
+    let m = cauchy(n, p, xs, ys).unwrap();
+
+    let scalings = (0..p).map(|r| {
+        let col_scales: Vec<GF_2_8> = (0..n).map(|j| m[n + r][j].mul_inv()).collect();
+        let normalized = scale(&m, &vec![GF_2_8::ONE; p], &col_scales).unwrap();
+
+        let row_scales: Vec<GF_2_8> = (0..p)
+            .map(|i| {
+                if i == r {
+                    return GF_2_8::ONE;
+                }
+                (1u8..=255)
+                    .map(GF_2_8::from)
+                    .min_by_key(|s| {
+                        (0..n)
+                            .map(|j| ones(normalized[n + i][j] * *s))
+                            .sum::<usize>()
+                    })
+                    .unwrap()
+            })
+            .collect();
+        let scaled = scale(&normalized, &row_scales, &vec![GF_2_8::ONE; n]).unwrap();
+
+        let nr = nr_ones(&scaled);
+        (scaled, nr)
+    });
+
+    scalings.min_by_key(|(_, nr)| *nr).unwrap()
+}
+
+/// A systematic Cauchy generator matrix for `parameter`,
+/// whose X, Y and scalings are searched to minimize `objective`.
+pub fn good_cauchy(
+    parameter: Parameter,
+    objective: CauchyObjective,
+) -> Result<Matrix<GF_2_8>, XorSlpError> {
+    parameter.check()?;
+    let (n, p) = (parameter.nr_data_block, parameter.nr_parity_block);
+
+    // X ++ Y
+    let mut elems: Vec<GF_2_8> = (0..n + p).map(|e| GF_2_8::from(e as u8)).collect();
+    let (m, mut best) = best_scaling(n, p, &elems[..p], &elems[p..]);
+    let mut pool = vec![m];
+
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for pos in 0..n + p {
+            for e in 0u8..=255 {
+                let e = GF_2_8::from(e);
+                if elems.contains(&e) {
+                    continue;
+                }
+                let mut candidate = elems.clone();
+                candidate[pos] = e;
+                let (m, nr) = best_scaling(n, p, &candidate[..p], &candidate[p..]);
+                if nr < best {
+                    best = nr;
+                    elems = candidate;
+                    pool.push(m);
+                    improved = true;
+                }
+            }
+        }
+    }
+
+    // the improvements are in the descending order of the ones
+    let pool = pool.split_off(pool.len().saturating_sub(POOL_SIZE));
+    match objective {
+        CauchyObjective::Ones => Ok(pool.last().unwrap().clone()),
+        CauchyObjective::Xors => {
+            let mut scored = Vec::new();
+            for m in pool.into_iter().rev() {
+                let nr = nr_xors(&m)?;
+                scored.push((m, nr));
+            }
+            Ok(scored.into_iter().min_by_key(|(_, nr)| *nr).unwrap().0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    fn is_mds(m: &Matrix<GF_2_8>) -> bool {
+        let (n, p) = (m.width(), m.height() - m.width());
+        (0..n + p).combinations(p).all(|remove| {
+            let mut tmp = m.clone();
+            tmp.drop_rows(remove);
+            tmp.inverse().is_some()
+        })
+    }
+
+    #[test]
+    fn cauchy_test() {
+        let elems: Vec<GF_2_8> = (0u8..10).map(|e| (e * 7 + 3).into()).collect();
+        let m = cauchy(6, 4, &elems[..4], &elems[4..]).unwrap();
+        assert!(is_mds(&m));
+
+        let scaled = scale(&m, &elems[..4], &elems[4..]).unwrap();
+        assert!(is_mds(&scaled));
+        assert!(scale(&m, &elems[..4], &[GF_2_8::ZERO; 6]).is_none());
+
+        let mut dup = elems.clone();
+        dup[0] = dup[9];
+        assert!(cauchy(6, 4, &dup[..4], &dup[4..]).is_none());
+        assert!(cauchy(6, 3, &elems[..4], &elems[4..]).is_none());
+    }
+
+    #[test]
+    fn good_cauchy_test() {
+        let parameter = Parameter {
+            nr_data_block: 3,
+            nr_parity_block: 2,
+        };
+        let elems: Vec<GF_2_8> = (0u8..5).map(GF_2_8::from).collect();
+        let plain = cauchy(3, 2, &elems[..2], &elems[2..]).unwrap();
+
+        let ones = good_cauchy(parameter, CauchyObjective::Ones).unwrap();
+        assert!(is_mds(&ones));
+        assert!(nr_ones(&ones) < nr_ones(&plain));
+        assert!(nr_ones(&ones) <= best_scaling(3, 2, &elems[..2], &elems[2..]).1);
+
+        let xors = good_cauchy(parameter, CauchyObjective::Xors).unwrap();
+        assert!(is_mds(&xors));
+        assert!(nr_xors(&xors).unwrap() <= nr_xors(&ones).unwrap());
+        // each of the 8 * 5 rows of the bitmatrix needs one XOR less than its ones
+        assert!(nr_xors(&xors).unwrap() <= nr_ones(&xors) - 8 * 5);
+    }
+}
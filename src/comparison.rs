@@ -1,7 +1,14 @@
+use crate::decoder;
+use crate::error::XorSlpError;
 use crate::for_benchmark;
+use crate::pipeline::Options;
 use crate::slp;
 use crate::slp::SLP;
+use crate::stat::Stat;
+use clap::arg_enum;
 use itertools::Itertools;
+use std::fmt;
+use std::io::Write;
 
 /*
 #[derive(Debug, Clone, Copy)]
@@ -18,17 +25,17 @@ pub enum XorDirection {
     do_xor(&original_slp, XorDirection::Backward, SortOrder::LexLarge);
 */
 
-pub fn repair_comparison(slp: &SLP) {
+pub fn repair_comparison(slp: &SLP) -> Result<(), XorSlpError> {
     use crate::fast_repair::{self, SortOrder};
     use crate::xor_repair;
 
     let shrinked_slp = for_benchmark::shrink(slp);
 
     if slp.is_empty() {
-        return;
+        return Ok(());
     }
 
-    let nr_original = shrinked_slp.to_trivial_graph().len();
+    let nr_original = shrinked_slp.to_trivial_graph()?.len();
 
     let nr_xor1 = fast_repair::run_repair2(&shrinked_slp, SortOrder::LexSmall).len();
     let nr_xor2 = fast_repair::run_repair2(&shrinked_slp, SortOrder::LexLarge).len();
@@ -41,9 +48,11 @@ pub fn repair_comparison(slp: &SLP) {
 
     println!("Original = {} => R(<) = {}, R(>) = {}, X(<, <) = {}, X(<, >) = {}, X(>, <) = {}, X(>, >) = {}",
              nr_original, nr_xor1, nr_xor2, nr_xor3, nr_xor4, nr_xor5, nr_xor6);
+
+    Ok(())
 }
 
-pub fn compression_stat() {
+pub fn compression_stat() -> Result<(), XorSlpError> {
     use crate::{rsv_bitmatrix, vandermonde};
 
     let enc = vandermonde::rsv(10, 4);
@@ -51,143 +60,450 @@ pub fn compression_stat() {
     let enc_slp = slp::SLP::build_from_bitmatrix_not_depending_variables(&bitmatrix_enc);
 
     print!("Enc: ");
-    repair_comparison(&enc_slp);
+    repair_comparison(&enc_slp)?;
 
     for it in (0..14).combinations(4) {
         let remove: Vec<usize> = it.to_vec();
-        let mut tmp = enc.clone();
-        tmp.drop_rows(remove.clone());
-        let inv = tmp.inverse().unwrap();
+        let inv = decoder::decode_matrix(&enc, &remove)?;
         let bitmatrix_inv = rsv_bitmatrix::matrix_to_bitmatrix(&inv);
         let inv_slp = slp::SLP::build_from_bitmatrix_not_depending_variables(&bitmatrix_inv);
 
         print!("Dec {:?}:", remove);
-        repair_comparison(&inv_slp);
+        repair_comparison(&inv_slp)?;
     }
+
+    Ok(())
+}
+
+/// A program without compression, compressed by RePair and compressed by XorRePair
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressStat {
+    pub no_comp: Stat,
+    pub repair: Stat,
+    pub xor_repair: Stat,
+}
+
+/// A program, compressed or not, before and after fusion and scheduling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllStat {
+    pub compress: bool,
+    pub no_fusion: Stat,
+    pub fusion: Stat,
+    pub scheduled: Stat, // fusioned and scheduled
+    pub nr_statements: usize,
+}
+
+/// The columns P, Co(P), Fu(Co(P)) and Dfs(Fu(Co(P))) of the table of Section 7.5
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sec75Stat {
+    pub original: Stat,
+    pub compressed: Stat,
+    pub fusion: Stat,
+    pub scheduled: Stat,
 }
 
-pub fn compress_stat(original_slp: &slp::SLP) {
+// `None` for a trivial program, i.e., one which needs no XOR
+pub fn compress_stat(original_slp: &slp::SLP) -> Result<Option<CompressStat>, XorSlpError> {
     let shrinked_slp = for_benchmark::shrink(original_slp);
 
     if shrinked_slp.is_empty() {
-        println!("This is a trivial case: We need no computation, and there is no statistics");
-        return;
+        return Ok(None);
     }
 
-    let slp = shrinked_slp.to_trivial_graph();
-    let (stat, _) = for_benchmark::graph_analyze(&shrinked_slp, &slp);
-    let slp_xor_num = stat.nr_xors;
+    let slp = shrinked_slp.to_trivial_graph()?;
+    let (no_comp, _) = for_benchmark::graph_analyze(&shrinked_slp, &slp)?;
 
     let repaired_slp = for_benchmark::repair(&shrinked_slp);
-    let (stat, _) = for_benchmark::graph_analyze(&shrinked_slp, &repaired_slp);
-    let repaired_xor_num = stat.nr_xors;
+    let (repair, _) = for_benchmark::graph_analyze(&shrinked_slp, &repaired_slp)?;
 
     let xor_repaired_slp = for_benchmark::xor_repair(&shrinked_slp);
-    let (stat, _) = for_benchmark::graph_analyze(&shrinked_slp, &xor_repaired_slp);
-    let xor_repaired_xor_num = stat.nr_xors;
+    let (xor_repair, _) = for_benchmark::graph_analyze(&shrinked_slp, &xor_repaired_slp)?;
 
-    println!(
-        "  [NoComp] #XOR = {}, [RePair] #XOR = {}, [XorRePair] #XOR = {}",
-        slp_xor_num, repaired_xor_num, xor_repaired_xor_num
-    );
+    Ok(Some(CompressStat {
+        no_comp,
+        repair,
+        xor_repair,
+    }))
 }
 
-pub fn all_stat(original_slp: &slp::SLP, compress: bool) {
+/// `options.scheduler` and `options.block_size` are used by the scheduling;
+/// `compress` is used instead of `options.compress`.
+pub fn all_stat(
+    original_slp: &slp::SLP,
+    compress: bool,
+    options: &Options,
+) -> Result<Option<AllStat>, XorSlpError> {
     let shrinked_slp = for_benchmark::shrink(original_slp);
 
     if shrinked_slp.is_empty() {
-        println!("This is a trivial case: We need no computation, and there is no statistics");
-        return;
+        return Ok(None);
     }
 
     let slp = if compress {
         for_benchmark::xor_repair(&shrinked_slp)
     } else {
-        shrinked_slp.to_trivial_graph()
+        shrinked_slp.to_trivial_graph()?
     };
 
-    let (orig_stat, _) = for_benchmark::graph_analyze(&shrinked_slp, &slp);
-    let xor_num = orig_stat.nr_xors;
-    let base_mem_num = orig_stat.nr_memacc;
-    let naive_page = orig_stat.nr_page_transfer;
+    let (no_fusion, _) = for_benchmark::graph_analyze(&shrinked_slp, &slp)?;
+    let (fusion, _) = for_benchmark::bench_fusion(&shrinked_slp, &slp)?;
+    let (scheduled, pebble_program) =
+        for_benchmark::bench_pebble(&shrinked_slp, &slp, options.nr_pebbles(), options.scheduler)?;
+
+    if no_fusion.nr_xors != fusion.nr_xors
+        || no_fusion.nr_xors != scheduled.nr_xors
+        || fusion.nr_memacc != scheduled.nr_memacc
+    {
+        return Err(XorSlpError::VerificationFailed(
+            "fusion or scheduling changed the number of XORs or memory accesses".to_owned(),
+        ));
+    }
 
-    let (fusion_stat, _) = for_benchmark::bench_fusion(&shrinked_slp, &slp);
-    let mem_num = fusion_stat.nr_memacc;
-    let fusioned_page = fusion_stat.nr_page_transfer;
+    Ok(Some(AllStat {
+        compress,
+        no_fusion,
+        fusion,
+        scheduled,
+        nr_statements: pebble_program.len(),
+    }))
+}
 
-    let (_, _, _, pebble_stat4, pebble_program) = for_benchmark::bench_pebble(&shrinked_slp, &slp);
-    let scheduled_page = pebble_stat4.nr_page_transfer;
+/// `options.scheduler` and `options.block_size` are used by the scheduling.
+pub fn sec75_stat(
+    original_slp: &slp::SLP,
+    options: &Options,
+) -> Result<Option<Sec75Stat>, XorSlpError> {
+    let shrinked_slp = for_benchmark::shrink(original_slp);
 
-    assert!(xor_num == fusion_stat.nr_xors);
-    assert!(xor_num == pebble_stat4.nr_xors);
-    assert!(mem_num == pebble_stat4.nr_memacc);
+    if shrinked_slp.is_empty() {
+        return Ok(None);
+    }
 
-    let comp_or_not = if compress {
-        "With comp.".to_owned()
-    } else {
-        "WithOUT comp.".to_owned()
-    };
+    // no compression
+    let slp = shrinked_slp.to_trivial_graph()?;
+    let (original, _) = for_benchmark::graph_analyze(&shrinked_slp, &slp)?;
+
+    let compressed_slp = for_benchmark::xor_repair(&shrinked_slp);
+    let (compressed, _) = for_benchmark::graph_analyze(&shrinked_slp, &compressed_slp)?;
+
+    let (fusion, _) = for_benchmark::bench_fusion(&shrinked_slp, &compressed_slp)?;
+
+    let (scheduled, _) = for_benchmark::bench_pebble(
+        &shrinked_slp,
+        &compressed_slp,
+        options.nr_pebbles(),
+        options.scheduler,
+    )?;
+
+    Ok(Some(Sec75Stat {
+        original,
+        compressed,
+        fusion,
+        scheduled,
+    }))
+}
 
-    println!(
-        "[{}] #XOR = {}, #MemAcc = {}, #[Fusioned]MemAcc = {},
+impl fmt::Display for CompressStat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "  [NoComp] #XOR = {}, [RePair] #XOR = {}, [XorRePair] #XOR = {}",
+            self.no_comp.nr_xors, self.repair.nr_xors, self.xor_repair.nr_xors
+        )
+    }
+}
+
+impl fmt::Display for AllStat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let comp_or_not = if self.compress {
+            "With comp."
+        } else {
+            "WithOUT comp."
+        };
+
+        write!(
+            f,
+            "[{}] #XOR = {}, #MemAcc = {}, #[Fusioned]MemAcc = {},
   #[NoFusion]CacheTrans = {}, #[Fusioned]CacheTrans = {}, #[Fusioned&Scheduled]CacheTrans = {},
   #[NoFusion]Variables = {}, #[Fusioned]Variables = {}, #[Fusioned&Scheduled]Variables = {},
   #[NoFusion]Capacity = {}, #[Fusioned]Capacity = {}, #[Fusioned&Scheduled]Capacity = {},
   #Statements = {}",
-        comp_or_not,
-        xor_num,
-        base_mem_num,
-        mem_num,
-        naive_page,
-        fusioned_page,
-        scheduled_page,
-        orig_stat.nr_variables,
-        fusion_stat.nr_variables,
-        pebble_stat4.nr_variables,
-        orig_stat.required_cache_capacity,
-        fusion_stat.required_cache_capacity,
-        pebble_stat4.required_cache_capacity,
-        pebble_program.len()
-    );
-}
-
-pub fn sec75_stat(original_slp: &slp::SLP) {
-    let shrinked_slp = for_benchmark::shrink(original_slp);
+            comp_or_not,
+            self.no_fusion.nr_xors,
+            self.no_fusion.nr_memacc,
+            self.fusion.nr_memacc,
+            self.no_fusion.nr_page_transfer,
+            self.fusion.nr_page_transfer,
+            self.scheduled.nr_page_transfer,
+            self.no_fusion.nr_variables,
+            self.fusion.nr_variables,
+            self.scheduled.nr_variables,
+            self.no_fusion.required_cache_capacity,
+            self.fusion.required_cache_capacity,
+            self.scheduled.required_cache_capacity,
+            self.nr_statements
+        )
+    }
+}
 
-    if shrinked_slp.is_empty() {
-        println!("This is a trivial case: We need no computation, and there is no statistics");
-        return;
+impl fmt::Display for Sec75Stat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (p, co, fu, dfs) = (
+            &self.original,
+            &self.compressed,
+            &self.fusion,
+            &self.scheduled,
+        );
+        writeln!(f, "        P   Co(P)   Fu(Co(P))   Dfs(Fu(Co(P)))")?;
+        writeln!(
+            f,
+            "#XOR {:4} {:7} {:11} {:16}",
+            p.nr_xors, co.nr_xors, fu.nr_xors, dfs.nr_xors
+        )?;
+        writeln!(
+            f,
+            "#MEM {:4} {:7} {:11} {:16}",
+            p.nr_memacc, co.nr_memacc, fu.nr_memacc, dfs.nr_memacc
+        )?;
+        writeln!(
+            f,
+            "NVar {:4} {:7} {:11} {:16}",
+            p.nr_variables, co.nr_variables, fu.nr_variables, dfs.nr_variables
+        )?;
+        write!(
+            f,
+            "CCap {:4} {:7} {:11} {:16}",
+            p.required_cache_capacity,
+            co.required_cache_capacity,
+            fu.required_cache_capacity,
+            dfs.required_cache_capacity
+        )
     }
+}
 
-    // no compression
-    let slp = shrinked_slp.to_trivial_graph();
-    let (orig, _) = for_benchmark::graph_analyze(&shrinked_slp, &slp);
-
-    let compressed = for_benchmark::xor_repair(&shrinked_slp);
-    let (comp, _) = for_benchmark::graph_analyze(&shrinked_slp, &compressed);
-
-    let (fusion, _) = for_benchmark::bench_fusion(&shrinked_slp, &compressed);
-
-    let (_, _, _, sched, _) = for_benchmark::bench_pebble(&shrinked_slp, &compressed);
-
-    println!("        P   Co(P)   Fu(Co(P))   Dfs(Fu(Co(P)))");
-    println!(
-        "#XOR {:4} {:7} {:11} {:16}",
-        orig.nr_xors, comp.nr_xors, fusion.nr_xors, sched.nr_xors
-    );
-    println!(
-        "#MEM {:4} {:7} {:11} {:16}",
-        orig.nr_memacc, comp.nr_memacc, fusion.nr_memacc, sched.nr_memacc
-    );
-    println!(
-        "NVar {:4} {:7} {:11} {:16}",
-        orig.nr_variables, comp.nr_variables, fusion.nr_variables, sched.nr_variables
-    );
-    println!(
-        "CCap {:4} {:7} {:11} {:16}",
-        orig.required_cache_capacity,
-        comp.required_cache_capacity,
-        fusion.required_cache_capacity,
-        sched.required_cache_capacity
-    );
+/*
+ * Machine readable output.
+ * A row is the statistics of the encoding program (no erased block) or a decoding program,
+ * flattened into columns named <variant>_<field of Stat>, e.g., fusion_nr_memacc.
+ * Every value is an unsigned integer or a boolean, which is written as it is in JSON and CSV.
+ * JSON output is an array of objects with the keys "program", "erased" and the columns,
+ * and CSV output has the header "program,erased,<columns>" with space separated erased blocks.
+ */
+
+arg_enum! {
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum OutputFormat {
+        Text, // the human readable one
+        Json,
+        Csv,
+    }
+}
+
+pub trait Columns: fmt::Display {
+    fn columns(&self) -> Vec<(String, String)>;
+}
+
+fn stat_columns(variant: &str, stat: &Stat) -> Vec<(String, String)> {
+    let fields = [
+        ("nr_xors", stat.nr_xors),
+        ("nr_memacc", stat.nr_memacc),
+        ("nr_page_transfer", stat.nr_page_transfer),
+        ("required_cache_capacity", stat.required_cache_capacity),
+        ("nr_variables", stat.nr_variables),
+    ];
+    fields
+        .iter()
+        .map(|(field, v)| (format!("{}_{}", variant, field), v.to_string()))
+        .collect()
+}
+
+impl Columns for CompressStat {
+    fn columns(&self) -> Vec<(String, String)> {
+        let mut columns = stat_columns("no_comp", &self.no_comp);
+        columns.extend(stat_columns("repair", &self.repair));
+        columns.extend(stat_columns("xor_repair", &self.xor_repair));
+        columns
+    }
+}
+
+impl Columns for AllStat {
+    fn columns(&self) -> Vec<(String, String)> {
+        let mut columns = vec![("compress".to_owned(), self.compress.to_string())];
+        columns.extend(stat_columns("no_fusion", &self.no_fusion));
+        columns.extend(stat_columns("fusion", &self.fusion));
+        columns.extend(stat_columns("scheduled", &self.scheduled));
+        columns.push(("nr_statements".to_owned(), self.nr_statements.to_string()));
+        columns
+    }
+}
+
+impl Columns for Sec75Stat {
+    fn columns(&self) -> Vec<(String, String)> {
+        let mut columns = stat_columns("original", &self.original);
+        columns.extend(stat_columns("compressed", &self.compressed));
+        columns.extend(stat_columns("fusion", &self.fusion));
+        columns.extend(stat_columns("scheduled", &self.scheduled));
+        columns
+    }
+}
+
+/// Writes rows of statistics in a format; all the rows should have the same columns.
+pub struct StatWriter<W: Write> {
+    out: W,
+    format: OutputFormat,
+    nr_rows: usize,
+    last_erased: Option<Vec<usize>>, // for the labels of the text format
+}
+
+impl<W: Write> StatWriter<W> {
+    pub fn new(out: W, format: OutputFormat) -> Self {
+        StatWriter {
+            out,
+            format,
+            nr_rows: 0,
+            last_erased: None,
+        }
+    }
+
+    /// A line only for the text format
+    pub fn heading(&mut self, line: &str) -> Result<(), XorSlpError> {
+        if self.format == OutputFormat::Text {
+            writeln!(self.out, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    /// A row for the program which recovers `erased`, or `None` for a trivial program.
+    /// The text format labels the row unless the previous one is for the same program.
+    pub fn write<T: Columns>(
+        &mut self,
+        erased: &[usize],
+        stat: Option<&T>,
+    ) -> Result<(), XorSlpError> {
+        let program = if erased.is_empty() { "enc" } else { "dec" };
+
+        match (self.format, stat) {
+            (OutputFormat::Text, _) => {
+                if self.last_erased.as_deref() != Some(erased) {
+                    if erased.is_empty() {
+                        writeln!(self.out, "Enc: ")?;
+                    } else {
+                        writeln!(self.out, "Dec {:?}:", erased)?;
+                    }
+                }
+                match stat {
+                    Some(stat) => writeln!(self.out, "{}", stat)?,
+                    None => writeln!(
+                        self.out,
+                        "This is a trivial case: We need no computation, and there is no statistics"
+                    )?,
+                }
+                self.last_erased = Some(erased.to_vec());
+            }
+            (_, None) => (), // there is nothing to measure
+            (OutputFormat::Json, Some(stat)) => {
+                let erased = erased.iter().map(|e| e.to_string()).join(",");
+                let columns = stat
+                    .columns()
+                    .into_iter()
+                    .map(|(name, v)| format!(", \"{}\": {}", name, v))
+                    .join("");
+                let sep = if self.nr_rows == 0 { "[" } else { "," };
+                writeln!(
+                    self.out,
+                    "{}\n  {{\"program\": \"{}\", \"erased\": [{}]{}}}",
+                    sep, program, erased, columns
+                )?;
+            }
+            (OutputFormat::Csv, Some(stat)) => {
+                let columns = stat.columns();
+                if self.nr_rows == 0 {
+                    let names = columns.iter().map(|(name, _)| name).join(",");
+                    writeln!(self.out, "program,erased,{}", names)?;
+                }
+                let values = columns.iter().map(|(_, v)| v).join(",");
+                writeln!(
+                    self.out,
+                    "{},{},{}",
+                    program,
+                    erased.iter().join(" "),
+                    values
+                )?;
+            }
+        }
+
+        if stat.is_some() {
+            self.nr_rows += 1;
+        }
+        Ok(())
+    }
+
+    /// Closes the JSON array; the writer must be finished after the last row
+    pub fn finish(mut self) -> Result<W, XorSlpError> {
+        if self.format == OutputFormat::Json {
+            if self.nr_rows == 0 {
+                writeln!(self.out, "[]")?;
+            } else {
+                writeln!(self.out, "]")?;
+            }
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rsv_bitmatrix, vandermonde};
+
+    #[test]
+    fn stat_writer_test() {
+        let enc = vandermonde::isa_rsv(3, 2);
+        let bitmatrix = rsv_bitmatrix::matrix_to_bitmatrix(&enc);
+        let enc_slp = SLP::build_from_bitmatrix_not_depending_variables(&bitmatrix);
+        let without = all_stat(&enc_slp, false, &Options::default())
+            .unwrap()
+            .unwrap();
+        let with = all_stat(&enc_slp, true, &Options::default())
+            .unwrap()
+            .unwrap();
+        assert!(with.no_fusion.nr_xors <= without.no_fusion.nr_xors);
+
+        let write = |format| {
+            let mut writer = StatWriter::new(Vec::new(), format);
+            writer.heading("Statistics").unwrap();
+            writer.write(&[], Some(&without)).unwrap();
+            writer.write::<AllStat>(&[0, 1], None).unwrap();
+            writer.write(&[2, 4], Some(&with)).unwrap();
+            String::from_utf8(writer.finish().unwrap()).unwrap()
+        };
+
+        let text = write(OutputFormat::Text);
+        assert!(text.starts_with("Statistics\nEnc: \n[WithOUT comp.] #XOR = "));
+        assert!(text.contains("Dec [0, 1]:\nThis is a trivial case"));
+        assert!(text.contains("Dec [2, 4]:\n[With comp.] #XOR = "));
+
+        let csv = write(OutputFormat::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("program,erased,compress,no_fusion_nr_xors,"));
+        assert!(lines[1].starts_with(&format!("enc,,false,{},", without.no_fusion.nr_xors)));
+        assert!(lines[2].starts_with(&format!("dec,2 4,true,{},", with.no_fusion.nr_xors)));
+        assert!(lines.iter().all(|l| l.split(',').count() == 19));
+
+        let json = write(OutputFormat::Json);
+        assert!(
+            json.starts_with("[\n  {\"program\": \"enc\", \"erased\": [], \"compress\": false, ")
+        );
+        assert!(
+            json.contains(",\n  {\"program\": \"dec\", \"erased\": [2,4], \"compress\": true, ")
+        );
+        assert!(json.ends_with(&format!("\"nr_statements\": {}}}\n]\n", with.nr_statements)));
+
+        let empty = StatWriter::new(Vec::new(), OutputFormat::Json)
+            .finish()
+            .unwrap();
+        assert_eq!(empty, b"[]\n");
+    }
 }
@@ -0,0 +1,233 @@
+use crate::decoder::{Decoder, DEFAULT_CACHE_CAPACITY};
+use crate::encoder::Encoder;
+use crate::error::XorSlpError;
+use crate::pipeline::Options;
+use crate::Parameter;
+use itertools::Itertools;
+
+/*
+ * Silently corrupted shards are located by a subset search:
+ * for t = 1, 2, ..., floor(nr_parity_block / 2),
+ * every set of t shards is regarded as erased and recovered from the others,
+ * and the set is the corrupted one iff the recovered stripe passes `Encoder::verify`.
+ *
+ * If the generator matrix is MDS, i.e., every nr_data_block rows of it are independent,
+ * the minimum distance is nr_parity_block + 1 and
+ * at most one such set of size <= floor(nr_parity_block / 2) exists.
+ * `vandermonde::isa_rsv` is not MDS for every parameter, however,
+ * so all the sets of the smallest size t having such a set are tried.
+ * The corruption is ambiguous if two of them pass, or if a set of size <= t can not be decoded,
+ * since such a set can not be ruled out.
+ *
+ * This needs at most sum_t C(nr_data_block + nr_parity_block, t) decoding programs (`nr_patterns`),
+ * and is intended for small nr_parity_block.
+ * Unless the decoder caches all of them (see `decoder`),
+ * every search evicts and optimizes them again.
+ */
+
+/// The number of erasure patterns which the search may decode,
+/// i.e., sum_t C(nr_data_block + nr_parity_block, t) for t = 1, ..., floor(nr_parity_block / 2).
+pub fn nr_patterns(parameter: Parameter) -> usize {
+    let n = parameter.nr_data_block + parameter.nr_parity_block;
+    (1..=parameter.nr_parity_block / 2)
+        .map(|t| (0..t).fold(1, |c, i| c * (n - i) / (i + 1)))
+        .sum()
+}
+
+/// A decoder whose cache keeps the programs of all the `nr_patterns` patterns,
+/// which should be passed to `locate` and `correct`.
+pub fn decoder(parameter: Parameter, options: Options) -> Result<Decoder, XorSlpError> {
+    Decoder::with_capacity(
+        parameter,
+        options,
+        std::cmp::max(nr_patterns(parameter), DEFAULT_CACHE_CAPACITY),
+    )
+}
+
+fn check_parameters(encoder: &Encoder, decoder: &Decoder) -> Result<(), XorSlpError> {
+    if encoder.parameter() != decoder.parameter() {
+        return Err(XorSlpError::InvalidParameter(format!(
+            "the encoder is for {:?} but the decoder is for {:?}",
+            encoder.parameter(),
+            decoder.parameter()
+        )));
+    }
+    Ok(())
+}
+
+// The corrupted shards and their recovered contents
+fn search(
+    encoder: &Encoder,
+    decoder: &Decoder,
+    shards: &[&[u8]],
+) -> Result<(Vec<usize>, Vec<Vec<u8>>), XorSlpError> {
+    check_parameters(encoder, decoder)?;
+
+    let mismatch = match encoder.verify(shards) {
+        Ok(()) => return Ok((Vec::new(), Vec::new())),
+        Err(e @ XorSlpError::Mismatch(_)) => e,
+        Err(e) => return Err(e),
+    };
+
+    let nr_shards = shards.len();
+    let mut work: Vec<Vec<u8>> = shards.iter().map(|s| s.to_vec()).collect();
+    // the sets which pass, with their recovered contents, and those which can not be decoded
+    let mut found: Vec<(Vec<usize>, Vec<Vec<u8>>)> = Vec::new();
+    let mut undecodable: Vec<Vec<usize>> = Vec::new();
+
+    for t in 1..=encoder.parameter().nr_parity_block / 2 {
+        for erased in (0..nr_shards).combinations(t) {
+            let present: Vec<bool> = (0..nr_shards).map(|i| !erased.contains(&i)).collect();
+            {
+                let mut candidate: Vec<&mut [u8]> = work.iter_mut().map(|s| &mut s[..]).collect();
+                match decoder.reconstruct(&mut candidate, &present) {
+                    Ok(()) => (),
+                    Err(XorSlpError::NonInvertible(_)) => {
+                        undecodable.push(erased);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            let candidate: Vec<&[u8]> = work.iter().map(|s| &s[..]).collect();
+            if encoder.verify(&candidate).is_ok() {
+                let recovered = erased.iter().map(|&i| work[i].clone()).collect();
+                found.push((erased.clone(), recovered));
+            }
+
+            for &i in &erased {
+                work[i].copy_from_slice(shards[i]);
+            }
+        }
+
+        if !found.is_empty() {
+            if found.len() == 1 && undecodable.is_empty() {
+                return Ok(found.pop().unwrap());
+            }
+            let mut suspects: Vec<usize> = found
+                .into_iter()
+                .map(|(erased, _)| erased)
+                .chain(undecodable)
+                .flatten()
+                .collect();
+            suspects.sort_unstable();
+            suspects.dedup();
+            return Err(XorSlpError::Mismatch(suspects));
+        }
+    }
+
+    Err(mismatch)
+}
+
+/// The indices of the corrupted shards, in ascending order.
+///
+/// `shards` consists of the data shards followed by the parity shards as in `Encoder::verify`.
+/// If no set of at most `nr_parity_block / 2` shards explains the corruption,
+/// the `XorSlpError::Mismatch` of `Encoder::verify` is returned.
+/// If the smallest explaining set is not unique (see the comment at the top of this module),
+/// `XorSlpError::Mismatch` lists the shards of all the candidate sets.
+/// `decoder` should be made by `decoder`, so that the programs of the search stay cached.
+pub fn locate(
+    encoder: &Encoder,
+    decoder: &Decoder,
+    shards: &[&[u8]],
+) -> Result<Vec<usize>, XorSlpError> {
+    Ok(search(encoder, decoder, shards)?.0)
+}
+
+/// Repair the corrupted shards in place and return their indices (see `locate`).
+pub fn correct(
+    encoder: &Encoder,
+    decoder: &Decoder,
+    shards: &mut [&mut [u8]],
+) -> Result<Vec<usize>, XorSlpError> {
+    let (corrupted, recovered) = {
+        let shards: Vec<&[u8]> = shards.iter().map(|s| &s[..]).collect();
+        search(encoder, decoder, &shards)?
+    };
+
+    for (&i, shard) in corrupted.iter().zip(recovered) {
+        shards[i].copy_from_slice(&shard);
+    }
+    Ok(corrupted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{OptimizeLevel, Options};
+    use crate::*;
+
+    #[test]
+    fn correct_test() {
+        let parameter = Parameter {
+            nr_data_block: 3,
+            nr_parity_block: 4,
+        };
+        // the search compiles many decoding programs
+        let options = Options {
+            level: OptimizeLevel::Nooptim,
+            ..Options::default()
+        };
+        let encoder = Encoder::new(parameter, options).unwrap();
+        let decoder = decoder(parameter, options).unwrap();
+
+        let len = 8 * 64 * 2;
+        let mut original = vec![0u8; len * 7];
+        fill_by_random(&mut original[..len * 3]);
+        {
+            let (data, parity) = original.split_at_mut(len * 3);
+            let data_shards: Vec<&[u8]> = data.chunks(len).collect();
+            let mut parity_shards: Vec<&mut [u8]> = parity.chunks_mut(len).collect();
+            encoder.encode(&data_shards, &mut parity_shards).unwrap();
+        }
+
+        let corrupt = |shards: &mut Vec<u8>, targets: &[usize]| {
+            for &i in targets {
+                shards[len * i + 5] ^= 0x21;
+                shards[len * i + len - 1] ^= 0x80;
+            }
+        };
+
+        for corrupted in [vec![], vec![1], vec![5], vec![0, 2], vec![2, 6]] {
+            let mut shards = original.clone();
+            corrupt(&mut shards, &corrupted);
+
+            assert_eq!(
+                locate(&encoder, &decoder, &shards.chunks(len).collect::<Vec<_>>()),
+                Ok(corrupted.clone())
+            );
+            let mut chunks: Vec<&mut [u8]> = shards.chunks_mut(len).collect();
+            assert_eq!(correct(&encoder, &decoder, &mut chunks), Ok(corrupted));
+            assert!(shards == original);
+        }
+
+        // beyond floor(4 / 2) corrupted shards
+        let mut shards = original.clone();
+        for i in [0, 3, 4] {
+            fill_by_random(&mut shards[len * i..len * (i + 1)]);
+        }
+        let mut chunks: Vec<&mut [u8]> = shards.chunks_mut(len).collect();
+        assert!(matches!(
+            correct(&encoder, &decoder, &mut chunks),
+            Err(XorSlpError::Mismatch(_))
+        ));
+        // the failed search has tried every pattern, and none of them is evicted
+        assert_eq!(nr_patterns(parameter), 7 + 21);
+        assert_eq!(decoder.nr_cached(), nr_patterns(parameter));
+
+        let other = Decoder::new(
+            Parameter {
+                nr_data_block: 3,
+                nr_parity_block: 2,
+            },
+            options,
+        )
+        .unwrap();
+        assert!(matches!(
+            locate(&encoder, &other, &original.chunks(len).collect::<Vec<_>>()),
+            Err(XorSlpError::InvalidParameter(_))
+        ));
+    }
+}
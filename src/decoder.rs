@@ -0,0 +1,531 @@
+use crate::bitmatrix::BitMatrix;
+use crate::bundle::Bundle;
+use crate::error::XorSlpError;
+use crate::fin_field::GF_2_8;
+use crate::matrix::Matrix;
+use crate::pipeline::{self, Options};
+use crate::program_file::ProgramFile;
+use crate::reorder::GenericRecentlyUse;
+use crate::run::{Program, RunError};
+use crate::*;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+pub const DEFAULT_CACHE_CAPACITY: usize = 64;
+
+/// The blocks used for decoding when `erased` are lost:
+/// the first `generator.width()` blocks which are not erased.
+/// Data blocks come first, so fewer of them have to be recomputed.
+pub fn survivors(generator: &Matrix<GF_2_8>, erased: &[usize]) -> Result<Vec<usize>, XorSlpError> {
+    if let Some(e) = erased.iter().find(|&&e| e >= generator.height()) {
+        return Err(XorSlpError::InvalidParameter(format!(
+            "block {} does not exist",
+            e
+        )));
+    }
+
+    let survivors: Vec<usize> = (0..generator.height())
+        .filter(|i| !erased.contains(i))
+        .take(generator.width())
+        .collect();
+    if survivors.len() < generator.width() {
+        return Err(XorSlpError::InvalidParameter(format!(
+            "at most {} blocks can be erased (now {:?})",
+            generator.height() - generator.width(),
+            erased
+        )));
+    }
+    Ok(survivors)
+}
+
+/// The inverse of the rows `survivors(generator, erased)` of `generator`,
+/// which maps the surviving blocks to the data blocks.
+pub fn decode_matrix(
+    generator: &Matrix<GF_2_8>,
+    erased: &[usize],
+) -> Result<Matrix<GF_2_8>, XorSlpError> {
+    let survivors = survivors(generator, erased)?;
+
+    let mut m = generator.clone();
+    m.drop_rows(
+        (0..generator.height())
+            .filter(|i| !survivors.contains(i))
+            .collect(),
+    );
+    m.inverse()
+        .ok_or_else(|| XorSlpError::NonInvertible(erased.to_vec()))
+}
+
+/// The rows `wanted` of `generator * decode_matrix(generator, erased)`,
+/// which map the surviving blocks to the wanted blocks.
+///
+/// Since `generator` is systematic, the rows for data blocks are those of the inverse,
+/// and the rows for parity blocks are the parity rows of `generator` composed with the inverse.
+pub fn repair_matrix(
+    generator: &Matrix<GF_2_8>,
+    erased: &[usize],
+    wanted: &[usize],
+) -> Result<Matrix<GF_2_8>, XorSlpError> {
+    let inv = decode_matrix(generator, erased)?;
+
+    let mut repair = generator * &inv;
+    repair.drop_rows(
+        (0..generator.height())
+            .filter(|i| !wanted.contains(i))
+            .collect(),
+    );
+    Ok(repair)
+}
+
+/// The bitmatrix of `repair_matrix`, which the programs of `Decoder::subset_program` compute.
+///
+/// `erased` must be sorted, and `wanted` must be a sorted subset of `erased`.
+pub fn repair_bitmatrix(
+    generator: &Matrix<GF_2_8>,
+    erased: &[usize],
+    wanted: &[usize],
+) -> Result<BitMatrix, XorSlpError> {
+    if !erased.windows(2).all(|w| w[0] < w[1]) {
+        return Err(XorSlpError::InvalidParameter(format!(
+            "erased blocks {:?} are not sorted",
+            erased
+        )));
+    }
+    if !wanted.windows(2).all(|w| w[0] < w[1]) || wanted.iter().any(|w| !erased.contains(w)) {
+        return Err(XorSlpError::InvalidParameter(format!(
+            "wanted blocks {:?} are not sorted erased blocks of {:?}",
+            wanted, erased
+        )));
+    }
+
+    let repair = repair_matrix(generator, erased, wanted)?;
+    Ok(rsv_bitmatrix::matrix_to_bitmatrix(&repair))
+}
+
+// (sorted erased blocks, sorted wanted blocks) for `Decoder`
+pub type CacheKey = (Vec<usize>, Vec<usize>);
+
+/*
+ * Compiled decoding programs keyed by the sorted erasure set and the wanted blocks
+ * (`lrc::LrcDecoder` keys them by the targets and the inputs of a repair instead).
+ * When more than `capacity` programs are cached, the least recently used one is dropped.
+ */
+pub struct ProgramCache {
+    capacity: usize,
+    recent: GenericRecentlyUse<CacheKey>,
+    programs: BTreeMap<CacheKey, Arc<Program>>,
+}
+
+impl ProgramCache {
+    pub fn new(capacity: usize) -> Self {
+        ProgramCache {
+            capacity,
+            recent: GenericRecentlyUse::new(),
+            programs: BTreeMap::new(),
+        }
+    }
+
+    pub fn get(&mut self, key: &CacheKey) -> Option<Arc<Program>> {
+        let program = self.programs.get(key).cloned();
+        if program.is_some() {
+            self.recent.access(key.clone());
+        }
+        program
+    }
+
+    pub fn insert(&mut self, key: CacheKey, program: Arc<Program>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.recent.access(key.clone());
+        self.programs.insert(key, program);
+        while self.programs.len() > self.capacity {
+            let victim = self.recent.evict().unwrap();
+            self.programs.remove(&victim);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+}
+
+pub struct Decoder {
+    parameter: Parameter,
+    options: Options,
+    matrix: Matrix<GF_2_8>,
+    cache: Mutex<ProgramCache>,
+    bundle: Option<Arc<Bundle>>,
+}
+
+impl Decoder {
+    pub fn new(parameter: Parameter, options: Options) -> Result<Self, XorSlpError> {
+        Self::with_capacity(parameter, options, DEFAULT_CACHE_CAPACITY)
+    }
+
+    /// `capacity` is the maximum number of erasure patterns whose programs are kept.
+    pub fn with_capacity(
+        parameter: Parameter,
+        options: Options,
+        capacity: usize,
+    ) -> Result<Self, XorSlpError> {
+        parameter.check()?;
+
+        let matrix = vandermonde::isa_rsv(parameter.nr_data_block, parameter.nr_parity_block);
+
+        Ok(Decoder {
+            parameter,
+            options,
+            matrix,
+            cache: Mutex::new(ProgramCache::new(capacity)),
+            bundle: None,
+        })
+    }
+
+    /// Take the programs of `bundle` instead of optimizing them,
+    /// except for those recovering only some of the erased shards.
+    pub fn with_bundle(mut self, bundle: Arc<Bundle>) -> Result<Self, XorSlpError> {
+        if bundle.parameter() != self.parameter {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "the bundle is for {:?}",
+                bundle.parameter()
+            )));
+        }
+        self.bundle = Some(bundle);
+        Ok(self)
+    }
+
+    pub fn parameter(&self) -> Parameter {
+        self.parameter
+    }
+
+    /// The number of programs currently cached
+    pub fn nr_cached(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+
+    /// The program recovering the erased shards.
+    ///
+    /// `erased` must be sorted and consist of at most `nr_parity_block` distinct shard indices.
+    /// The inputs of the program are the shards `survivors(erased)`,
+    /// and the outputs are the erased shards in ascending order.
+    pub fn program(&self, erased: &[usize]) -> Result<Arc<Program>, XorSlpError> {
+        self.subset_program(erased, erased)
+    }
+
+    /// The shards read by the programs for `erased`, in ascending order (see `decoder::survivors`).
+    pub fn survivors(&self, erased: &[usize]) -> Result<Vec<usize>, XorSlpError> {
+        survivors(&self.matrix, erased)
+    }
+
+    /// The program recovering only the erased shards `wanted` (sorted).
+    ///
+    /// The outputs are the wanted shards in ascending order.
+    /// Since only the rows for `wanted` are compiled,
+    /// the program is smaller than `program(erased)`.
+    pub fn subset_program(
+        &self,
+        erased: &[usize],
+        wanted: &[usize],
+    ) -> Result<Arc<Program>, XorSlpError> {
+        let key = (erased.to_vec(), wanted.to_vec());
+        if let Some(program) = self.cache.lock().unwrap().get(&key) {
+            return Ok(program);
+        }
+
+        // compile without holding the lock; other patterns can be served meanwhile
+        let program = Arc::new(self.compile(erased, wanted)?);
+        self.cache.lock().unwrap().insert(key, program.clone());
+        Ok(program)
+    }
+
+    /// Put a precomputed program into the cache, so that it is not optimized again.
+    pub fn preload(&self, file: &ProgramFile) -> Result<(), XorSlpError> {
+        if file.parameter() != self.parameter {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "the program is for {:?}",
+                file.parameter()
+            )));
+        }
+
+        let program = file.compile()?.with_threads(self.options.nr_threads);
+        let key = (file.erased().to_vec(), file.wanted().to_vec());
+        self.cache.lock().unwrap().insert(key, Arc::new(program));
+        Ok(())
+    }
+
+    fn compile(&self, erased: &[usize], wanted: &[usize]) -> Result<Program, XorSlpError> {
+        if let Some(bundle) = &self.bundle {
+            if !erased.is_empty() && erased[..] == wanted[..] {
+                let program = bundle.lookup(erased)?.compile()?;
+                return Ok(program.with_threads(self.options.nr_threads));
+            }
+        }
+
+        // a single SLP, so that XorRePair finds the XORs shared by data and parity rows
+        let bitmatrix = repair_bitmatrix(&self.matrix, erased, wanted)?;
+        pipeline::compile_bitmatrix(&bitmatrix, &self.options)
+    }
+
+    /// Recover the shards `i` with `!present[i]` in place.
+    ///
+    /// `shards` consists of the data shards followed by the parity shards,
+    /// and at most `nr_parity_block` of them may be missing.
+    /// As in `Encoder::encode`, the shards must have the same length, which is a multiple of 8.
+    pub fn reconstruct(
+        &self,
+        shards: &mut [&mut [u8]],
+        present: &[bool],
+    ) -> Result<(), XorSlpError> {
+        let wanted: Vec<usize> = (0..present.len()).filter(|&i| !present[i]).collect();
+        self.reconstruct_subset(shards, present, &wanted)
+    }
+
+    /// Recover only the missing shards `wanted` in place, e.g., to serve a read.
+    ///
+    /// The other missing shards are left untouched. See `reconstruct` for `shards` and `present`.
+    pub fn reconstruct_subset(
+        &self,
+        shards: &mut [&mut [u8]],
+        present: &[bool],
+        wanted: &[usize],
+    ) -> Result<(), XorSlpError> {
+        let nr_data = self.parameter.nr_data_block;
+        let nr_shards = nr_data + self.parameter.nr_parity_block;
+
+        if shards.len() != nr_shards {
+            return Err(RunError::WrongNumberOfInputs {
+                expected: nr_shards,
+                actual: shards.len(),
+            }
+            .into());
+        }
+        if present.len() != nr_shards {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "the mask has {} entries for {} shards",
+                present.len(),
+                nr_shards
+            )));
+        }
+
+        let erased: Vec<usize> = (0..present.len()).filter(|&i| !present[i]).collect();
+        let survivors = self.survivors(&erased)?;
+        let program = self.subset_program(&erased, wanted)?;
+
+        let mut inputs: Vec<&[u8]> = Vec::new();
+        let mut outputs: Vec<&mut [u8]> = Vec::new();
+        for (i, shard) in shards.iter_mut().enumerate() {
+            if survivors.contains(&i) {
+                inputs.push(&**shard);
+            } else if wanted.contains(&i) {
+                outputs.push(&mut **shard);
+            }
+        }
+
+        if outputs.is_empty() {
+            return Ok(());
+        }
+        Ok(program.run_on_shards(&inputs, &mut outputs)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::Encoder;
+    use crate::run::PageAlignedArray;
+
+    const PARAM: Parameter = Parameter {
+        nr_data_block: 4,
+        nr_parity_block: 2,
+    };
+
+    fn encoded(len: usize) -> PageAlignedArray {
+        let mut shards = PageAlignedArray::new(len * 6).unwrap();
+        let (data, parity) = shards.as_mut_slice().split_at_mut(len * 4);
+        fill_by_random(data);
+
+        let encoder = Encoder::new(PARAM, Options::default()).unwrap();
+        let data_shards: Vec<&[u8]> = data.chunks(len).collect();
+        let mut parity_shards: Vec<&mut [u8]> = parity.chunks_mut(len).collect();
+        encoder.encode(&data_shards, &mut parity_shards).unwrap();
+
+        shards
+    }
+
+    #[test]
+    fn reconstruct_test() {
+        let len = 8 * BLOCK_SIZE_PER_ITER * 2;
+        let mut shards = encoded(len);
+        let original = shards.as_slice().to_vec();
+
+        let decoder = Decoder::new(PARAM, Options::default()).unwrap();
+
+        for erased in [&[0, 2][..], &[1, 4], &[3, 5], &[1], &[5], &[]] {
+            let mut present = vec![true; 6];
+            for &e in erased {
+                present[e] = false;
+                shards.as_mut_slice()[e * len..(e + 1) * len].fill(0);
+            }
+
+            let mut s = shards.split_mut(6);
+            decoder.reconstruct(&mut s, &present).unwrap();
+
+            // both data and parity shards are available again
+            assert!(shards.as_slice() == &original[..], "{:?}", erased);
+        }
+
+        assert_eq!(decoder.nr_cached(), 6);
+
+        // data shards are preferred
+        assert_eq!(decoder.survivors(&[1]).unwrap(), vec![0, 2, 3, 4]);
+        assert_eq!(decoder.survivors(&[0, 2]).unwrap(), vec![1, 3, 4, 5]);
+    }
+
+    #[test]
+    fn reconstruct_subset_test() {
+        let len = 8 * BLOCK_SIZE_PER_ITER;
+        let mut shards = encoded(len);
+        let original = shards.as_slice().to_vec();
+
+        let decoder = Decoder::new(PARAM, Options::default()).unwrap();
+
+        shards.as_mut_slice()[..len].fill(0);
+        shards.as_mut_slice()[2 * len..3 * len].fill(0);
+        let present = [false, true, false, true, true, true];
+
+        let mut s = shards.split_mut(6);
+        decoder.reconstruct_subset(&mut s, &present, &[2]).unwrap();
+        assert!(shards.as_slice()[2 * len..3 * len] == original[2 * len..3 * len]);
+        assert!(shards.as_slice()[..len].iter().all(|b| *b == 0));
+
+        let erased = vec![0, 2];
+        let full = decoder.program(&erased).unwrap();
+        let subset = decoder.subset_program(&erased, &[2]).unwrap();
+        assert_eq!(subset.nr_outputs(), 8);
+        let nr_xors = |p: &Program| p.code().iter().map(|(_, vs)| vs.len()).sum::<usize>();
+        assert!(nr_xors(&subset) < nr_xors(&full));
+
+        // a wanted shard must be erased, and the wanted shards must be sorted
+        for wanted in [&[1][..], &[4], &[2, 0]] {
+            assert!(matches!(
+                decoder.subset_program(&erased, wanted),
+                Err(XorSlpError::InvalidParameter(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn repair_program_test() {
+        let decoder = Decoder::new(PARAM, Options::default()).unwrap();
+        let erased = vec![1, 5];
+        let repair = repair_matrix(&decoder.matrix, &erased, &erased).unwrap();
+
+        // the same rows optimized separately
+        let mut data = repair.clone();
+        data.drop_rows(vec![1]);
+        let mut parity = repair.clone();
+        parity.drop_rows(vec![0]);
+        let separate = pipeline::compile_bitmatrices(
+            &[
+                rsv_bitmatrix::matrix_to_bitmatrix(&data),
+                rsv_bitmatrix::matrix_to_bitmatrix(&parity),
+            ],
+            &Options::default(),
+        )
+        .unwrap();
+
+        let combined = decoder.program(&erased).unwrap();
+        let nr_xors = |p: &Program| p.code().iter().map(|(_, vs)| vs.len()).sum::<usize>();
+        assert!(nr_xors(&combined) < nr_xors(&separate));
+    }
+
+    #[test]
+    fn object_round_trip_test() {
+        let encoder = Encoder::new(PARAM, Options::default()).unwrap();
+        let decoder = Decoder::new(PARAM, Options::default()).unwrap();
+
+        for object_len in [1, 12345, 8 * BLOCK_SIZE_PER_ITER * 4 + 3] {
+            let mut object = vec![0u8; object_len];
+            fill_by_random(&mut object);
+
+            let len = encoder.shard_len(object_len);
+            let mut shards: Vec<Vec<u8>> = object.chunks(len).map(|c| c.to_vec()).collect();
+            shards.resize(6, Vec::new());
+            for s in shards.iter_mut() {
+                s.resize(len, 0);
+            }
+
+            let (data, parity) = shards.split_at_mut(4);
+            let data: Vec<&[u8]> = data.iter().map(|s| &s[..]).collect();
+            let mut parity: Vec<&mut [u8]> = parity.iter_mut().map(|s| &mut s[..]).collect();
+            encoder.encode(&data, &mut parity).unwrap();
+
+            shards[1].fill(0);
+            shards[3].fill(0);
+            let mut s: Vec<&mut [u8]> = shards.iter_mut().map(|s| &mut s[..]).collect();
+            decoder
+                .reconstruct(&mut s, &[true, false, true, false, true, true])
+                .unwrap();
+
+            let mut decoded = shards[..4].concat();
+            decoded.truncate(object_len);
+            assert!(decoded == object);
+        }
+    }
+
+    #[test]
+    fn cache_eviction_test() {
+        let decoder = Decoder::with_capacity(PARAM, Options::default(), 2).unwrap();
+
+        let p1 = decoder.program(&[0, 1]).unwrap();
+        decoder.program(&[4, 5]).unwrap();
+        // [0, 1] becomes more recent than [4, 5]
+        assert!(Arc::ptr_eq(&p1, &decoder.program(&[0, 1]).unwrap()));
+
+        // [4, 5] is evicted
+        decoder.program(&[2, 3]).unwrap();
+        assert_eq!(decoder.nr_cached(), 2);
+        assert!(Arc::ptr_eq(&p1, &decoder.program(&[0, 1]).unwrap()));
+        let cache = decoder.cache.lock().unwrap();
+        assert!(!cache.programs.contains_key(&(vec![4, 5], vec![4, 5])));
+    }
+
+    #[test]
+    fn error_test() {
+        use crate::field::Field;
+        use crate::matrix::MatrixSize;
+
+        let decoder = Decoder::new(PARAM, Options::default()).unwrap();
+        assert!(matches!(
+            decoder.program(&[0, 1, 2]),
+            Err(XorSlpError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            decoder.program(&[1, 0]),
+            Err(XorSlpError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            decoder.program(&[0, 6]),
+            Err(XorSlpError::InvalidParameter(_))
+        ));
+
+        // the first two rows are the same
+        let mut m = Matrix::<GF_2_8>::new(MatrixSize {
+            height: 3,
+            width: 2,
+        });
+        m[0][0] = GF_2_8::ONE;
+        m[1][0] = GF_2_8::ONE;
+        m[2][1] = GF_2_8::ONE;
+        assert_eq!(
+            decode_matrix(&m, &[2]),
+            Err(XorSlpError::NonInvertible(vec![2]))
+        );
+        assert!(decode_matrix(&m, &[0]).is_ok());
+    }
+}
@@ -0,0 +1,428 @@
+use crate::bitmatrix::BitMatrix;
+use crate::error::XorSlpError;
+use crate::fin_field::GF_2_8;
+use crate::matrix::Matrix;
+use crate::pipeline::{self, Options};
+use crate::run::{CheckProgram, Program, RunError};
+use crate::*;
+use std::sync::{Arc, Mutex};
+
+/// `Encoder::shard_len` for `parameter`
+pub fn shard_len(parameter: Parameter, object_len: usize) -> usize {
+    let len = object_len.div_ceil(parameter.nr_data_block);
+    len.div_ceil(8) * 8
+}
+
+/*
+ * Each shard is regarded as 8 bit-planes of the same width:
+ * shard = [plane_0 | plane_1 | ... | plane_7],
+ * and the bitmatrix of the generator matrix acts on these planes.
+ */
+pub struct Encoder {
+    parameter: Parameter,
+    options: Options,
+    matrix: Matrix<GF_2_8>,
+    bitmatrix: BitMatrix, // of the parity rows
+    program: Program,
+    // compiled on demand by `column_program`
+    column_programs: Mutex<Vec<Option<Arc<Program>>>>,
+    // compiled on demand by `verify`
+    check_program: Mutex<Option<Arc<CheckProgram>>>,
+}
+
+impl Encoder {
+    /// Build the optimized encoding program for the `isa_rsv` generator matrix.
+    pub fn new(parameter: Parameter, options: Options) -> Result<Self, XorSlpError> {
+        parameter.check()?;
+
+        let matrix = vandermonde::isa_rsv(parameter.nr_data_block, parameter.nr_parity_block);
+
+        let mut parity = matrix.clone();
+        parity.drop_rows((0..parameter.nr_data_block).collect());
+        let bitmatrix = rsv_bitmatrix::matrix_to_bitmatrix(&parity);
+
+        let program = pipeline::compile_bitmatrix(&bitmatrix, &options)?;
+
+        Ok(Encoder {
+            parameter,
+            options,
+            matrix,
+            bitmatrix,
+            program,
+            column_programs: Mutex::new(vec![None; parameter.nr_data_block]),
+            check_program: Mutex::new(None),
+        })
+    }
+
+    pub fn parameter(&self) -> Parameter {
+        self.parameter
+    }
+
+    /// The (data + parity) x data generator matrix
+    pub fn matrix(&self) -> &Matrix<GF_2_8> {
+        &self.matrix
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// The length of each shard for an object of `object_len` bytes.
+    ///
+    /// The object is split into `nr_data_block` shards padded with zeros.
+    pub fn shard_len(&self, object_len: usize) -> usize {
+        shard_len(self.parameter, object_len)
+    }
+
+    /// Compute `parity_shards` from `data_shards`.
+    ///
+    /// All the shards must have the same length, which is a multiple of 8 (see `shard_len`).
+    pub fn encode(
+        &self,
+        data_shards: &[&[u8]],
+        parity_shards: &mut [&mut [u8]],
+    ) -> Result<(), XorSlpError> {
+        if data_shards.len() != self.parameter.nr_data_block {
+            return Err(RunError::WrongNumberOfInputs {
+                expected: self.parameter.nr_data_block,
+                actual: data_shards.len(),
+            }
+            .into());
+        }
+        if parity_shards.len() != self.parameter.nr_parity_block {
+            return Err(RunError::WrongNumberOfOutputs {
+                expected: self.parameter.nr_parity_block,
+                actual: parity_shards.len(),
+            }
+            .into());
+        }
+
+        Ok(self.program.run_on_shards(data_shards, parity_shards)?)
+    }
+
+    /// Check that the parity shards agree with the data shards.
+    ///
+    /// `shards` consists of the data shards followed by the parity shards.
+    /// The parity is recomputed and compared block by block without being stored,
+    /// and `XorSlpError::Mismatch` reports the parity shards which disagree.
+    pub fn verify(&self, shards: &[&[u8]]) -> Result<(), XorSlpError> {
+        let nr_shards = self.parameter.nr_data_block + self.parameter.nr_parity_block;
+        if shards.len() != nr_shards {
+            return Err(RunError::WrongNumberOfInputs {
+                expected: nr_shards,
+                actual: shards.len(),
+            }
+            .into());
+        }
+
+        let mismatches = self.check_program()?.run_on_shards(shards)?;
+        if mismatches.is_empty() {
+            return Ok(());
+        }
+
+        // each parity shard consists of 8 outputs
+        let mut parity: Vec<usize> = mismatches
+            .iter()
+            .map(|o| self.parameter.nr_data_block + o / 8)
+            .collect();
+        parity.dedup();
+        Err(XorSlpError::Mismatch(parity))
+    }
+
+    // The parity bitmatrix followed by the identity for the stored parity,
+    // whose outputs are zero iff the parity is consistent.
+    fn check_program(&self) -> Result<Arc<CheckProgram>, XorSlpError> {
+        if let Some(program) = &*self.check_program.lock().unwrap() {
+            return Ok(program.clone());
+        }
+
+        let nr_parity_rows = self.bitmatrix.height();
+        let syndrome = BitMatrix::from_nested_vecs(
+            self.bitmatrix
+                .inner
+                .iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    let mut row = row.clone();
+                    row.extend((0..nr_parity_rows).map(|j| i == j));
+                    row
+                })
+                .collect(),
+        );
+        let program = pipeline::compile_bitmatrix(&syndrome, &self.options)?;
+        let program = Arc::new(CheckProgram::new(&program));
+        *self.check_program.lock().unwrap() = Some(program.clone());
+        Ok(program)
+    }
+
+    /// The program XORing the parity changes for a change of the data shard `index` into the parity shards.
+    ///
+    /// It is compiled from the 8 columns of the parity bitmatrix for the shard
+    /// by `pipeline::compile_bitmatrix_accumulate`, and cached.
+    pub fn column_program(&self, index: usize) -> Result<Arc<Program>, XorSlpError> {
+        if index >= self.parameter.nr_data_block {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "data block {} does not exist",
+                index
+            )));
+        }
+        if let Some(program) = &self.column_programs.lock().unwrap()[index] {
+            return Ok(program.clone());
+        }
+
+        let columns = BitMatrix::from_nested_vecs(
+            self.bitmatrix
+                .inner
+                .iter()
+                .map(|row| row[index * 8..(index + 1) * 8].to_vec())
+                .collect(),
+        );
+        let program = Arc::new(pipeline::compile_bitmatrix_accumulate(
+            &columns,
+            &self.options,
+        )?);
+        self.column_programs.lock().unwrap()[index] = Some(program.clone());
+        Ok(program)
+    }
+
+    /// Update `parity_shards` in place for the data shard `index` changed by `delta` (= old ^ new).
+    ///
+    /// Since the code is linear, the new parity is the old one XOR the encoded delta.
+    pub fn apply_delta(
+        &self,
+        index: usize,
+        delta: &[u8],
+        parity_shards: &mut [&mut [u8]],
+    ) -> Result<(), XorSlpError> {
+        if parity_shards.len() != self.parameter.nr_parity_block {
+            return Err(RunError::WrongNumberOfOutputs {
+                expected: self.parameter.nr_parity_block,
+                actual: parity_shards.len(),
+            }
+            .into());
+        }
+        if parity_shards.iter().any(|s| s.len() != delta.len()) {
+            return Err(RunError::LengthMismatch.into());
+        }
+
+        let program = self.column_program(index)?;
+        program.run_on_shards(&[delta], parity_shards)?;
+        Ok(())
+    }
+
+    /// Update `parity_shards` in place for the data shard `index` rewritten from `old_data` to `new_data`.
+    pub fn update(
+        &self,
+        index: usize,
+        old_data: &[u8],
+        new_data: &[u8],
+        parity_shards: &mut [&mut [u8]],
+    ) -> Result<(), XorSlpError> {
+        if old_data.len() != new_data.len() {
+            return Err(RunError::LengthMismatch.into());
+        }
+        let delta: Vec<u8> = old_data.iter().zip(new_data).map(|(x, y)| x ^ y).collect();
+        self.apply_delta(index, &delta, parity_shards)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmatrix::BitMatrix;
+    use crate::run::PageAlignedArray;
+
+    // Apply `bitmatrix` to the bit-planes of `data` naively
+    pub(crate) fn naive_encode(bitmatrix: &BitMatrix, data: &[&[u8]]) -> Vec<Vec<u8>> {
+        let width = data[0].len() / 8;
+        let planes: Vec<&[u8]> = data.iter().flat_map(|s| s.chunks(width)).collect();
+
+        let mut out = Vec::new();
+        for row in &bitmatrix.inner {
+            let mut plane = vec![0u8; width];
+            for (c, b) in row.iter().enumerate() {
+                if *b {
+                    for (x, y) in plane.iter_mut().zip(planes[c]) {
+                        *x ^= y;
+                    }
+                }
+            }
+            out.push(plane);
+        }
+
+        out.chunks(8).map(|planes| planes.concat()).collect()
+    }
+
+    #[test]
+    fn encode_test() {
+        let parameter = Parameter {
+            nr_data_block: 4,
+            nr_parity_block: 2,
+        };
+        let encoder = Encoder::new(parameter, Options::default()).unwrap();
+
+        let len = 8 * BLOCK_SIZE_PER_ITER * 3;
+        let mut data = PageAlignedArray::new(len * 4).unwrap();
+        fill_by_random(data.as_mut_slice());
+        let mut parity = PageAlignedArray::new(len * 2).unwrap();
+
+        let data_shards = data.split(4);
+        let mut parity_shards = parity.split_mut(2);
+        encoder.encode(&data_shards, &mut parity_shards).unwrap();
+
+        let mut m = encoder.matrix().clone();
+        m.drop_rows((0..4).collect());
+        let expected = naive_encode(&rsv_bitmatrix::matrix_to_bitmatrix(&m), &data_shards);
+
+        for i in 0..2 {
+            assert!(parity_shards[i][..] == expected[i][..]);
+        }
+
+        for block_size in [64, 192] {
+            let options = Options {
+                block_size,
+                ..Options::default()
+            };
+            let encoder = Encoder::new(parameter, options).unwrap();
+            assert_eq!(encoder.program().block_size(), block_size);
+
+            let mut parity = PageAlignedArray::new(len * 2).unwrap();
+            let mut parity_shards = parity.split_mut(2);
+            encoder.encode(&data_shards, &mut parity_shards).unwrap();
+            for i in 0..2 {
+                assert!(parity_shards[i][..] == expected[i][..]);
+            }
+        }
+
+        assert!(matches!(
+            encoder.encode(&data_shards[..3], &mut parity_shards),
+            Err(XorSlpError::BufferMismatch(_))
+        ));
+        assert!(matches!(
+            Encoder::new(
+                Parameter {
+                    nr_data_block: 0,
+                    nr_parity_block: 2
+                },
+                Options::default()
+            ),
+            Err(XorSlpError::InvalidParameter(_))
+        ));
+    }
+
+    #[test]
+    fn update_test() {
+        let parameter = Parameter {
+            nr_data_block: 4,
+            nr_parity_block: 2,
+        };
+        let encoder = Encoder::new(parameter, Options::default()).unwrap();
+
+        let len = 8 * 64 * 3 + 8;
+        let mut data = vec![0u8; len * 4];
+        fill_by_random(&mut data);
+        let mut parity = vec![0u8; len * 2];
+        {
+            let data_shards: Vec<&[u8]> = data.chunks(len).collect();
+            let mut parity_shards: Vec<&mut [u8]> = parity.chunks_mut(len).collect();
+            encoder.encode(&data_shards, &mut parity_shards).unwrap();
+        }
+
+        for index in 0..4 {
+            let mut new_data = vec![0u8; len];
+            fill_by_random(&mut new_data);
+
+            let mut parity_shards: Vec<&mut [u8]> = parity.chunks_mut(len).collect();
+            encoder
+                .update(
+                    index,
+                    &data[index * len..(index + 1) * len],
+                    &new_data,
+                    &mut parity_shards,
+                )
+                .unwrap();
+            data[index * len..(index + 1) * len].copy_from_slice(&new_data);
+
+            let data_shards: Vec<&[u8]> = data.chunks(len).collect();
+            let mut expected = vec![0u8; len * 2];
+            let mut expected_shards: Vec<&mut [u8]> = expected.chunks_mut(len).collect();
+            encoder.encode(&data_shards, &mut expected_shards).unwrap();
+            assert!(parity == expected);
+        }
+
+        // aligned shards are updated without staging
+        let aligned_len = 8 * BLOCK_SIZE_PER_ITER * 2;
+        let mut aligned_data = PageAlignedArray::new(aligned_len * 4).unwrap();
+        fill_by_random(aligned_data.as_mut_slice());
+        let mut aligned_parity = PageAlignedArray::new(aligned_len * 2).unwrap();
+        let mut delta = PageAlignedArray::new(aligned_len).unwrap();
+        fill_by_random(delta.as_mut_slice());
+        {
+            let data_shards = aligned_data.split(4);
+            let mut parity_shards = aligned_parity.split_mut(2);
+            encoder.encode(&data_shards, &mut parity_shards).unwrap();
+            encoder
+                .apply_delta(1, delta.as_slice(), &mut parity_shards)
+                .unwrap();
+        }
+        for (x, d) in aligned_data.as_mut_slice()[aligned_len..2 * aligned_len]
+            .iter_mut()
+            .zip(delta.as_slice())
+        {
+            *x ^= d;
+        }
+        let mut expected = PageAlignedArray::new(aligned_len * 2).unwrap();
+        encoder
+            .encode(&aligned_data.split(4), &mut expected.split_mut(2))
+            .unwrap();
+        assert!(aligned_parity.as_slice() == expected.as_slice());
+
+        let mut parity_shards: Vec<&mut [u8]> = parity.chunks_mut(len).collect();
+        assert!(matches!(
+            encoder.apply_delta(4, &data[..len], &mut parity_shards),
+            Err(XorSlpError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            encoder.apply_delta(0, &data[..8], &mut parity_shards),
+            Err(XorSlpError::BufferMismatch(RunError::LengthMismatch))
+        ));
+    }
+
+    #[test]
+    fn verify_test() {
+        let parameter = Parameter {
+            nr_data_block: 4,
+            nr_parity_block: 2,
+        };
+        let encoder = Encoder::new(parameter, Options::default()).unwrap();
+
+        for len in [8 * 64 * 3, 8 * 64 * 2 + 40] {
+            let mut shards = vec![0u8; len * 6];
+            fill_by_random(&mut shards[..len * 4]);
+            {
+                let (data, parity) = shards.split_at_mut(len * 4);
+                let data_shards: Vec<&[u8]> = data.chunks(len).collect();
+                let mut parity_shards: Vec<&mut [u8]> = parity.chunks_mut(len).collect();
+                encoder.encode(&data_shards, &mut parity_shards).unwrap();
+            }
+            encoder
+                .verify(&shards.chunks(len).collect::<Vec<_>>())
+                .unwrap();
+
+            // a bit flip at the end of the last parity shard
+            shards[len * 6 - 1] ^= 0x80;
+            assert_eq!(
+                encoder.verify(&shards.chunks(len).collect::<Vec<_>>()),
+                Err(XorSlpError::Mismatch(vec![5]))
+            );
+            shards[len * 6 - 1] ^= 0x80;
+
+            // both parity shards depend on every data byte
+            shards[len + 3] ^= 1;
+            assert_eq!(
+                encoder.verify(&shards.chunks(len).collect::<Vec<_>>()),
+                Err(XorSlpError::Mismatch(vec![4, 5]))
+            );
+        }
+    }
+}
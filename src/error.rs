@@ -0,0 +1,105 @@
+use crate::run::RunError;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XorSlpError {
+    // e.g., no data blocks, or too many blocks for GF(2^8)
+    InvalidParameter(String),
+    // the surviving blocks can not recover the erased ones
+    NonInvertible(Vec<usize>),
+    // a bitmatrix/SLP file which can not be parsed
+    MalformedInput(String),
+    // the given buffers do not fit the program
+    BufferMismatch(RunError),
+    // an optimized program does not compute what it should
+    VerificationFailed(String),
+    // the parity blocks which do not agree with the data blocks
+    Mismatch(Vec<usize>),
+    // e.g., a missing file or a full disk
+    Io(IoError),
+}
+
+/// A `std::io::Error` shared between the clones of an `XorSlpError`.
+///
+/// Two of them are equal if they have the same kind and message.
+#[derive(Debug, Clone)]
+pub struct IoError(Arc<std::io::Error>);
+
+impl IoError {
+    pub fn kind(&self) -> std::io::ErrorKind {
+        self.0.kind()
+    }
+
+    pub fn get_ref(&self) -> &std::io::Error {
+        &self.0
+    }
+}
+
+impl PartialEq for IoError {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind() == other.kind() && self.0.to_string() == other.0.to_string()
+    }
+}
+
+impl Eq for IoError {}
+
+impl fmt::Display for XorSlpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XorSlpError::InvalidParameter(msg) => write!(f, "invalid parameter: {}", msg),
+            XorSlpError::NonInvertible(erased) => {
+                write!(f, "blocks {:?} can not be recovered", erased)
+            }
+            XorSlpError::MalformedInput(msg) => write!(f, "malformed input: {}", msg),
+            XorSlpError::BufferMismatch(e) => write!(f, "buffer mismatch: {}", e),
+            XorSlpError::VerificationFailed(msg) => write!(f, "verification failed: {}", msg),
+            XorSlpError::Mismatch(parity) => {
+                write!(f, "parity blocks {:?} are inconsistent", parity)
+            }
+            XorSlpError::Io(e) => write!(f, "I/O error: {}", e.get_ref()),
+        }
+    }
+}
+
+impl std::error::Error for XorSlpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            XorSlpError::BufferMismatch(e) => Some(e),
+            XorSlpError::Io(e) => Some(e.get_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<RunError> for XorSlpError {
+    fn from(e: RunError) -> Self {
+        XorSlpError::BufferMismatch(e)
+    }
+}
+
+impl From<std::io::Error> for XorSlpError {
+    fn from(e: std::io::Error) -> Self {
+        XorSlpError::Io(IoError(Arc::new(e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn io_error_test() {
+        let open =
+            |path: &str| -> Result<std::fs::File, XorSlpError> { Ok(std::fs::File::open(path)?) };
+        let e = open("/nonexistent/xorslp_ec").unwrap_err();
+        match &e {
+            XorSlpError::Io(io) => assert_eq!(io.kind(), std::io::ErrorKind::NotFound),
+            _ => panic!("{:?} is not an I/O error", e),
+        }
+        assert_eq!(e.clone(), e);
+        assert!(e.source().unwrap().is::<std::io::Error>());
+        assert!(e.to_string().starts_with("I/O error: "));
+    }
+}
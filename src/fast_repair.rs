@@ -39,7 +39,7 @@ fn pair_finder2(
 
     for i in 0..program.width() {
         for j in i + 1..program.width() {
-            let count = count_occurences2(i, j, &program);
+            let count = count_occurences2(i, j, program);
 
             match count_max.cmp(&count) {
                 Ordering::Equal => {
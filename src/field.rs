@@ -37,7 +37,7 @@ pub trait Field:
         }
 
         let v = self.exp(exponent / 2);
-        if exponent % 2 == 0 {
+        if exponent.is_multiple_of(2) {
             // x^{2n} = x^n * x^n
             v * v
         } else {
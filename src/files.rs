@@ -0,0 +1,466 @@
+use crate::decoder::Decoder;
+use crate::encoder::{self, Encoder};
+use crate::error::XorSlpError;
+use crate::pipeline::Options;
+use crate::shard_file::{crc32c, GeneratorKind, ShardHeader, HEADER_LEN};
+use crate::stream::EncodeStream;
+use crate::*;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/*
+ * A file is stored as the shard files `<prefix>.0`, ..., `<prefix>.{n-1}`,
+ * where the first nr_data_block ones are data shards.
+ * A shard file consists of the records of the stripes made by `EncodeStream`,
+ * each of which is a `ShardHeader` followed by the shard of the stripe.
+ *
+ * A record which is missing, corrupted, or belongs to another shard or object
+ * is an erasure of its stripe, so each stripe can lose nr_parity_block records.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Manifest {
+    pub parameter: Parameter,
+    pub generator: GeneratorKind,
+    pub shard_chunk: usize,
+    pub object_len: u64,
+}
+
+pub fn shard_path(prefix: &Path, index: usize) -> PathBuf {
+    let mut path = prefix.as_os_str().to_owned();
+    path.push(format!(".{}", index));
+    PathBuf::from(path)
+}
+
+impl Manifest {
+    fn from_header(header: &ShardHeader) -> Self {
+        Manifest {
+            parameter: header.parameter,
+            generator: header.generator,
+            shard_chunk: header.shard_chunk,
+            object_len: header.object_len,
+        }
+    }
+
+    pub fn nr_shards(&self) -> usize {
+        self.parameter.nr_data_block + self.parameter.nr_parity_block
+    }
+
+    /// The length of each shard in each stripe; an empty object has one empty stripe.
+    pub fn stripes(&self) -> Vec<usize> {
+        let stripe = (self.parameter.nr_data_block * self.shard_chunk) as u64;
+        let mut stripes = vec![self.shard_chunk; (self.object_len / stripe) as usize];
+        let rest = (self.object_len % stripe) as usize;
+        if rest > 0 || self.object_len == 0 {
+            stripes.push(encoder::shard_len(self.parameter, rest));
+        }
+        stripes
+    }
+
+    /// The length of each shard file
+    pub fn shard_file_len(&self) -> u64 {
+        self.stripes()
+            .iter()
+            .map(|&len| (HEADER_LEN + len) as u64)
+            .sum()
+    }
+
+    /// The header of the record for `stripe` of the shard `index`
+    pub fn header(&self, index: usize, stripe: usize, payload: &[u8]) -> ShardHeader {
+        ShardHeader {
+            parameter: self.parameter,
+            generator: self.generator,
+            index,
+            stripe: stripe as u64,
+            object_len: self.object_len,
+            shard_chunk: self.shard_chunk,
+            payload_len: payload.len(),
+            checksum: crc32c(payload),
+        }
+    }
+
+    /// The manifest on which most of the first records of the shard files of `prefix` agree
+    pub fn discover(prefix: &Path) -> Result<Self, XorSlpError> {
+        // the manifests and the lengths of the shard files which have them
+        let mut votes: BTreeMap<usize, (Manifest, Vec<u64>)> = BTreeMap::new();
+
+        // GF(2^8) does not allow more than 256 shards
+        for index in 0..256 {
+            let mut bytes = [0u8; HEADER_LEN];
+            let read = File::open(shard_path(prefix, index)).and_then(|mut f| {
+                f.read_exact(&mut bytes)?;
+                f.metadata()
+            });
+            let (header, file_len) = match read {
+                Ok(metadata) => match ShardHeader::from_bytes(&bytes) {
+                    Ok(header) if header.index == index && header.stripe == 0 => {
+                        (header, metadata.len())
+                    }
+                    _ => continue,
+                },
+                _ => continue,
+            };
+
+            let manifest = Manifest::from_header(&header);
+            let first = votes
+                .iter()
+                .find(|(_, (m, _))| *m == manifest)
+                .map_or(index, |(first, _)| *first);
+            votes
+                .entry(first)
+                .or_insert((manifest, Vec::new()))
+                .1
+                .push(file_len);
+        }
+
+        // ties are broken by the smallest shard index
+        let (_, (manifest, file_lens)) = votes
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, (_, file_lens))| file_lens.len())
+            .ok_or_else(|| {
+                XorSlpError::MalformedInput(format!("no shard of {:?} is readable", prefix))
+            })?;
+        manifest.parameter.check()?;
+        if manifest.shard_chunk == 0 || !manifest.shard_chunk.is_multiple_of(8) {
+            return Err(XorSlpError::MalformedInput(format!(
+                "shard chunk {} is not a positive multiple of 8",
+                manifest.shard_chunk
+            )));
+        }
+
+        // every stripe has a header, which bounds the stripes to be listed by `shard_file_len`
+        let stripe =
+            (manifest.parameter.nr_data_block as u64).saturating_mul(manifest.shard_chunk as u64);
+        let nr_stripes = std::cmp::max(manifest.object_len.div_ceil(stripe), 1);
+        let longest = file_lens.iter().max().cloned().unwrap_or(0);
+        if nr_stripes > longest / HEADER_LEN as u64
+            || !file_lens.contains(&manifest.shard_file_len())
+        {
+            return Err(XorSlpError::MalformedInput(format!(
+                "no shard file of {:?} has the length for {} bytes",
+                prefix, manifest.object_len
+            )));
+        }
+        if manifest.generator != GeneratorKind::IsaRsv {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "the {:?} generator matrix is not supported",
+                manifest.generator
+            )));
+        }
+        Ok(manifest)
+    }
+}
+
+/// Split `input` into the shard files of `prefix`, `shard_chunk` bytes per shard and stripe.
+pub fn encode_file(
+    input: &Path,
+    prefix: &Path,
+    encoder: &Encoder,
+    shard_chunk: usize,
+) -> Result<Manifest, XorSlpError> {
+    let file = File::open(input)?;
+    let manifest = Manifest {
+        parameter: encoder.parameter(),
+        generator: GeneratorKind::IsaRsv,
+        shard_chunk,
+        object_len: file.metadata()?.len(),
+    };
+    // every header fits if that of the last shard with a full payload does
+    ShardHeader {
+        payload_len: shard_chunk,
+        ..manifest.header(manifest.nr_shards() - 1, 0, &[])
+    }
+    .to_bytes()?;
+    let mut stream = EncodeStream::new(encoder, shard_chunk)?;
+
+    let mut sinks = (0..manifest.nr_shards())
+        .map(|i| Ok(BufWriter::new(File::create(shard_path(prefix, i))?)))
+        .collect::<Result<Vec<_>, XorSlpError>>()?;
+    let mut stripe = 0;
+    let object_len = stream.encode_stripes(&mut BufReader::new(file), |shards| {
+        for (index, (sink, shard)) in sinks.iter_mut().zip(shards).enumerate() {
+            sink.write_all(&manifest.header(index, stripe, shard).to_bytes()?)?;
+            sink.write_all(shard)?;
+        }
+        stripe += 1;
+        Ok(())
+    })?;
+    for sink in sinks.iter_mut() {
+        sink.flush()?;
+    }
+
+    if object_len != manifest.object_len {
+        return Err(XorSlpError::MalformedInput(format!(
+            "{:?} changed while being encoded",
+            input
+        )));
+    }
+    Ok(manifest)
+}
+
+// the readers of the shard files; a reader is dropped once its file ends
+struct Records {
+    manifest: Manifest,
+    readers: Vec<Option<BufReader<File>>>,
+}
+
+impl Records {
+    fn open(prefix: &Path, manifest: Manifest) -> Self {
+        let readers = (0..manifest.nr_shards())
+            .map(|i| File::open(shard_path(prefix, i)).ok().map(BufReader::new))
+            .collect();
+        Records { manifest, readers }
+    }
+
+    // The shards of `stripe`, where those of broken records are `None`
+    fn next(&mut self, stripe: usize, len: usize) -> Vec<Option<Vec<u8>>> {
+        let mut shards = Vec::new();
+        for (index, reader) in self.readers.iter_mut().enumerate() {
+            let mut record = vec![0u8; HEADER_LEN + len];
+            let read = reader.as_mut().map(|r| r.read_exact(&mut record));
+            let shard = match read {
+                Some(Ok(())) => {
+                    let (header, payload) = record.split_at(HEADER_LEN);
+                    let expected = self.manifest.header(index, stripe, payload);
+                    if ShardHeader::from_bytes(header) == Ok(expected) {
+                        Some(payload.to_vec())
+                    } else {
+                        None
+                    }
+                }
+                Some(Err(_)) => {
+                    *reader = None;
+                    None
+                }
+                None => None,
+            };
+            shards.push(shard);
+        }
+        shards
+    }
+}
+
+/// Restore the file of `prefix` into `output` from the shard files,
+/// and return the shards having erased records.
+///
+/// Only the erased data shards are recovered; each stripe may lose `nr_parity_block` records.
+/// The file is written to `partial_path(output)` and renamed to `output` only when it is restored,
+/// so that a failure leaves `output` as it was.
+pub fn decode_file(
+    prefix: &Path,
+    output: &Path,
+    options: Options,
+) -> Result<Vec<usize>, XorSlpError> {
+    let partial = partial_path(output);
+    match restore(prefix, &partial, options) {
+        Ok(erased_shards) => {
+            std::fs::rename(&partial, output)?;
+            Ok(erased_shards)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+/// The file in the directory of `output` which `decode_file` writes before renaming it to `output`.
+pub fn partial_path(output: &Path) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(".partial");
+    PathBuf::from(path)
+}
+
+// decode_file into `output` directly
+fn restore(prefix: &Path, output: &Path, options: Options) -> Result<Vec<usize>, XorSlpError> {
+    let manifest = Manifest::discover(prefix)?;
+    let nr_data = manifest.parameter.nr_data_block;
+    let decoder = Decoder::new(manifest.parameter, options)?;
+
+    let mut records = Records::open(prefix, manifest);
+    let mut erased_shards: Vec<usize> = Vec::new();
+    let mut writer = BufWriter::new(File::create(output)?);
+    let mut rest = manifest.object_len;
+    for (stripe, len) in manifest.stripes().into_iter().enumerate() {
+        let shards = records.next(stripe, len);
+        let present: Vec<bool> = shards.iter().map(|s| s.is_some()).collect();
+        let erased: Vec<usize> = (0..present.len()).filter(|&i| !present[i]).collect();
+        let wanted: Vec<usize> = erased.iter().cloned().filter(|&i| i < nr_data).collect();
+        erased_shards.extend(&erased);
+
+        let mut shards: Vec<Vec<u8>> = shards
+            .into_iter()
+            .map(|s| s.unwrap_or_else(|| vec![0u8; len]))
+            .collect();
+        if !wanted.is_empty() {
+            let mut shards: Vec<&mut [u8]> = shards.iter_mut().map(|s| &mut s[..]).collect();
+            decoder.reconstruct_subset(&mut shards, &present, &wanted)?;
+        }
+
+        // the last stripe is padded with zeros
+        let stripe_len = std::cmp::min(rest, (nr_data * len) as u64);
+        let mut to_write = stripe_len as usize;
+        for shard in &shards[..nr_data] {
+            let n = std::cmp::min(to_write, len);
+            writer.write_all(&shard[..n])?;
+            to_write -= n;
+        }
+        rest -= stripe_len;
+    }
+    writer.flush()?;
+
+    erased_shards.sort_unstable();
+    erased_shards.dedup();
+    Ok(erased_shards)
+}
+
+/// Check the shard files of `prefix`.
+///
+/// `XorSlpError::Mismatch` reports the shards which have broken records
+/// and the parity shards which disagree with the data shards in some stripe.
+pub fn verify_file(prefix: &Path, options: Options) -> Result<(), XorSlpError> {
+    let manifest = Manifest::discover(prefix)?;
+    let encoder = Encoder::new(manifest.parameter, options)?;
+
+    let mut records = Records::open(prefix, manifest);
+    let mut mismatches: Vec<usize> = Vec::new();
+    for (stripe, len) in manifest.stripes().into_iter().enumerate() {
+        let shards = records.next(stripe, len);
+        let broken: Vec<usize> = (0..shards.len()).filter(|&i| shards[i].is_none()).collect();
+        if !broken.is_empty() {
+            mismatches.extend(broken);
+            continue;
+        }
+
+        let shards: Vec<&[u8]> = shards.iter().map(|s| &s.as_ref().unwrap()[..]).collect();
+        match encoder.verify(&shards) {
+            Ok(()) => (),
+            Err(XorSlpError::Mismatch(parity)) => mismatches.extend(parity),
+            Err(e) => return Err(e),
+        }
+    }
+
+    mismatches.sort_unstable();
+    mismatches.dedup();
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(XorSlpError::Mismatch(mismatches))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_round_trip_test() {
+        let parameter = Parameter {
+            nr_data_block: 4,
+            nr_parity_block: 2,
+        };
+        let options = Options::default();
+        let encoder = Encoder::new(parameter, options).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("xorslp_files_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("input");
+        let output = dir.join("output");
+        let prefix = dir.join("shard");
+
+        let shard_chunk = 8 * 64 * 2;
+        for object_len in [0, 1000, 4 * shard_chunk, 4 * shard_chunk * 2 + 1234] {
+            let mut object = vec![0u8; object_len];
+            fill_by_random(&mut object);
+            std::fs::write(&input, &object).unwrap();
+
+            let manifest = encode_file(&input, &prefix, &encoder, shard_chunk).unwrap();
+            assert_eq!(Manifest::discover(&prefix).unwrap(), manifest);
+            assert_eq!(
+                std::fs::metadata(shard_path(&prefix, 3)).unwrap().len(),
+                manifest.shard_file_len()
+            );
+            verify_file(&prefix, options).unwrap();
+
+            assert_eq!(decode_file(&prefix, &output, options).unwrap(), vec![]);
+            assert!(std::fs::read(&output).unwrap() == object);
+
+            // a lost data shard and a truncated parity shard
+            std::fs::remove_file(shard_path(&prefix, 1)).unwrap();
+            let parity = std::fs::read(shard_path(&prefix, 5)).unwrap();
+            std::fs::write(shard_path(&prefix, 5), &parity[..parity.len() - 1]).unwrap();
+            assert_eq!(decode_file(&prefix, &output, options).unwrap(), vec![1, 5]);
+            assert!(std::fs::read(&output).unwrap() == object);
+            assert_eq!(
+                verify_file(&prefix, options),
+                Err(XorSlpError::Mismatch(vec![1, 5]))
+            );
+
+            // a failed decoding keeps the previous output
+            std::fs::remove_file(shard_path(&prefix, 2)).unwrap();
+            assert!(decode_file(&prefix, &output, options).is_err());
+            assert!(std::fs::read(&output).unwrap() == object);
+            assert!(!partial_path(&output).exists());
+        }
+
+        // corrupted records are erasures of their own stripes
+        encode_file(&input, &prefix, &encoder, shard_chunk).unwrap();
+        let record = HEADER_LEN + shard_chunk;
+        for (index, at) in [
+            (0, 100),
+            (3, record + 7),
+            (4, record + 2),
+            (5, 2 * record + 1),
+        ] {
+            let mut shard = std::fs::read(shard_path(&prefix, index)).unwrap();
+            shard[at] ^= 1;
+            std::fs::write(shard_path(&prefix, index), &shard).unwrap();
+        }
+        assert_eq!(
+            verify_file(&prefix, options),
+            Err(XorSlpError::Mismatch(vec![0, 3, 4, 5]))
+        );
+        assert_eq!(
+            decode_file(&prefix, &output, options).unwrap(),
+            vec![0, 3, 4, 5]
+        );
+        assert!(std::fs::read(&output).unwrap() == std::fs::read(&input).unwrap());
+
+        // swapped shard files are detected by their indices
+        std::fs::rename(shard_path(&prefix, 1), dir.join("tmp")).unwrap();
+        std::fs::rename(shard_path(&prefix, 2), shard_path(&prefix, 1)).unwrap();
+        std::fs::rename(dir.join("tmp"), shard_path(&prefix, 2)).unwrap();
+        assert!(decode_file(&prefix, &output, options).is_err());
+
+        // manifests which do not fit the shard files are rejected before their stripes are listed
+        let manifest = encode_file(&input, &prefix, &encoder, shard_chunk).unwrap();
+        let forge = |shard_chunk: usize, object_len: u64| {
+            for index in 0..manifest.nr_shards() {
+                let mut shard = std::fs::read(shard_path(&prefix, index)).unwrap();
+                let mut header = ShardHeader::from_bytes(&shard).unwrap();
+                header.shard_chunk = shard_chunk;
+                header.object_len = object_len;
+                shard[..HEADER_LEN].copy_from_slice(&header.to_bytes().unwrap());
+                std::fs::write(shard_path(&prefix, index), &shard).unwrap();
+            }
+        };
+        for (chunk, object_len) in [
+            (0, manifest.object_len),
+            (shard_chunk + 4, manifest.object_len),
+            (shard_chunk, u64::MAX),
+            (shard_chunk, manifest.object_len + 4 * shard_chunk as u64),
+        ] {
+            forge(chunk, object_len);
+            assert!(matches!(
+                Manifest::discover(&prefix),
+                Err(XorSlpError::MalformedInput(_))
+            ));
+        }
+        forge(manifest.shard_chunk, manifest.object_len);
+        assert_eq!(Manifest::discover(&prefix).unwrap(), manifest);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
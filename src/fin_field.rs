@@ -3,7 +3,6 @@ use crate::univariate_polynomial::*;
 use std::convert::{From, Into};
 use std::fmt::Debug;
 use std::ops::{Add, Div, Mul, Neg, Sub};
-use std::string::ToString;
 
 pub trait FiniteField: Field {
     // Since this is a finite field, we can enumerate all the elements.
@@ -160,9 +159,9 @@ lazy_static! {
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct GF_2_8(u8);
 
-impl ToString for GF_2_8 {
-    fn to_string(&self) -> String {
-        format!("{:#04x}", self.0)
+impl std::fmt::Display for GF_2_8 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#04x}", self.0)
     }
 }
 
@@ -412,7 +411,7 @@ mod tests {
     fn check_mul_inv<F: FiniteField>() {
         for e in F::enumerate() {
             if e != F::ZERO {
-                let inv = (&e).mul_inv();
+                let inv = e.mul_inv();
                 assert_eq!(e * inv, F::ONE);
             }
         }
@@ -1,5 +1,8 @@
+use crate::bitmatrix::BitMatrix;
+use crate::error::XorSlpError;
 use crate::fast_repair::SortOrder;
 use crate::fusion;
+use crate::pipeline::Scheduler;
 use crate::reorder::{self, Pebble, Strategy};
 use crate::reorder2;
 use crate::repair;
@@ -19,14 +22,20 @@ pub enum XorDirection {
 
 pub type PebbleProgram = Vec<(Pebble, Vec<Pebble>)>;
 
-fn rename(valuation: &Valuation, program: &[(Pebble, Vec<Pebble>)]) -> PebbleProgram {
+fn rename(
+    valuation: &Valuation,
+    program: &[(Pebble, Vec<Pebble>)],
+) -> Result<PebbleProgram, XorSlpError> {
     let mapping = validation::is_subvaluation(
-        &valuation,
+        valuation,
         &validation::pebble_computation_to_valuation(program),
     );
 
+    let mapping = mapping.ok_or_else(|| {
+        XorSlpError::VerificationFailed("the program does not compute the SLP".to_owned())
+    })?;
+
     let mapping: Vec<(usize, usize)> = mapping
-        .unwrap()
         .iter()
         .map(|(a, b)| (a.var_to_usize().unwrap(), b.var_to_usize().unwrap()))
         .collect();
@@ -35,54 +44,87 @@ fn rename(valuation: &Valuation, program: &[(Pebble, Vec<Pebble>)]) -> PebblePro
     let renamed =
         renaming::rename_multislp_by(&renaming, &reorder::pebble_slp_to_term_slp(program));
 
-    assert!(validation::is_strict_subvaluation(
-        &valuation,
-        &validation::term_computation_to_valuation(&renamed)
-    ));
+    if !validation::is_strict_subvaluation(
+        valuation,
+        &validation::term_computation_to_valuation(&renamed),
+    ) {
+        return Err(XorSlpError::VerificationFailed(
+            "the renamed program does not compute the SLP".to_owned(),
+        ));
+    }
+
+    Ok(reorder::term_slp_to_pebble_slp(&renamed))
+}
+
+// The values of the variables of `graph` over the constants of `shrinked_slp`.
+// `repair::evaluate_program` only knows the constants appearing in `graph`,
+// so the values are padded for the trailing constants which `shrinked_slp` does not use.
+fn evaluate(graph: &Graph, shrinked_slp: &SLP) -> SLP {
+    let evaluated = repair::evaluate_program(graph);
+    let nr_constants = shrinked_slp.num_of_original_constants();
+
+    let values: Vec<Vec<bool>> = (0..evaluated.num_of_variables())
+        .map(|var| {
+            let mut value = evaluated[var].clone();
+            value.resize(nr_constants, false);
+            value
+        })
+        .collect();
+
+    SLP::build_from_bitmatrix_not_depending_variables(&BitMatrix::from_nested_vecs(values))
+}
 
-    reorder::term_slp_to_pebble_slp(&renamed)
+fn realizes(evaluated: &SLP, shrinked_slp: &SLP) -> Result<Vec<(usize, usize)>, XorSlpError> {
+    repair::realizes(evaluated, shrinked_slp).ok_or_else(|| {
+        XorSlpError::VerificationFailed("the graph does not realize the SLP".to_owned())
+    })
 }
 
 pub fn shrink(original_slp: &SLP) -> SLP {
     optimize_slp::step1(original_slp)
 }
 
-pub fn to_ssa(shrinked_slp: &SLP) -> Graph {
-    let shrinked_program = shrinked_slp.to_trivial_graph();
+pub fn to_ssa(shrinked_slp: &SLP) -> Result<Graph, XorSlpError> {
+    let shrinked_program = shrinked_slp.to_trivial_graph()?;
 
-    fusion::slp_to_ssa(&shrinked_program)
+    Ok(fusion::slp_to_ssa(&shrinked_program))
 }
 
 pub fn repair(shrinked_slp: &SLP) -> Graph {
-    fast_repair::run_repair2(&shrinked_slp, SortOrder::LexSmall)
+    fast_repair::run_repair2(shrinked_slp, SortOrder::LexSmall)
 }
 
 pub fn xor_repair(shrinked_slp: &SLP) -> Graph {
-    xor_repair::run_xor_repair_reverse(&shrinked_slp, SortOrder::LexSmall)
+    xor_repair::run_xor_repair_reverse(shrinked_slp, SortOrder::LexSmall)
 }
 
-pub fn graph_analyze(shrinked_slp: &SLP, graph: &Graph) -> (Stat, PebbleProgram) {
-    let program: Vec<(Term, Vec<Term>)> = graph_to_multiterm_slp(&graph);
+pub fn graph_analyze(
+    shrinked_slp: &SLP,
+    graph: &Graph,
+) -> Result<(Stat, PebbleProgram), XorSlpError> {
+    let program: Vec<(Term, Vec<Term>)> = graph_to_multiterm_slp(graph);
     let program: Vec<(Pebble, Vec<Pebble>)> = reorder::term_slp_to_pebble_slp(&program);
     let stat = stat::analyze(&program);
 
-    let shrinked_valuation = validation::slp_to_valuation(&shrinked_slp);
-    let renamed = rename(&shrinked_valuation, &program);
+    let shrinked_valuation = validation::slp_to_valuation(shrinked_slp);
+    let renamed = rename(&shrinked_valuation, &program)?;
 
-    (stat, renamed)
+    Ok((stat, renamed))
 }
 
-pub fn bench_fusion(shrinked_slp: &SLP, graph: &Graph) -> (Stat, PebbleProgram) {
+pub fn bench_fusion(
+    shrinked_slp: &SLP,
+    graph: &Graph,
+) -> Result<(Stat, PebbleProgram), XorSlpError> {
     let graph = if fusion::is_ssa(graph) {
         graph.clone()
     } else {
         fusion::slp_to_ssa(graph)
     };
 
-    let evaluated = repair::evaluate_program(&graph);
+    let evaluated = evaluate(&graph, shrinked_slp);
 
-    let targets: Vec<Term> = repair::realizes(&evaluated, &shrinked_slp)
-        .unwrap()
+    let targets: Vec<Term> = realizes(&evaluated, shrinked_slp)?
         .iter()
         .map(|(a, _)| Term::Var(*a))
         .collect();
@@ -92,22 +134,28 @@ pub fn bench_fusion(shrinked_slp: &SLP, graph: &Graph) -> (Stat, PebbleProgram)
     let multislp: Vec<(Pebble, Vec<Pebble>)> = reorder::term_slp_to_pebble_slp(&multislp);
     let multislp_stat = stat::analyze(&multislp);
 
-    let shrinked_valuation = validation::slp_to_valuation(&shrinked_slp);
-    let renamed = rename(&shrinked_valuation, &multislp);
+    let shrinked_valuation = validation::slp_to_valuation(shrinked_slp);
+    let renamed = rename(&shrinked_valuation, &multislp)?;
 
-    (multislp_stat, renamed)
+    Ok((multislp_stat, renamed))
 }
 
-pub fn bench_pebble(shrinked_slp: &SLP, graph: &Graph) -> (Stat, Stat, Stat, Stat, PebbleProgram) {
+/// `nr_pebbles` is the number of blocks which the scheduler assumes to fit in the cache (`PEBBLE_NUM` by default).
+/// The returned statistics and program are those of the schedule by `scheduler`.
+pub fn bench_pebble(
+    shrinked_slp: &SLP,
+    graph: &Graph,
+    nr_pebbles: usize,
+    scheduler: Scheduler,
+) -> Result<(Stat, PebbleProgram), XorSlpError> {
     let graph = if fusion::is_ssa(graph) {
         graph.clone()
     } else {
         fusion::slp_to_ssa(graph)
     };
-    let evaluated = repair::evaluate_program(&graph);
+    let evaluated = evaluate(&graph, shrinked_slp);
 
-    let targets: Vec<Term> = repair::realizes(&evaluated, &shrinked_slp)
-        .unwrap()
+    let targets: Vec<Term> = realizes(&evaluated, shrinked_slp)?
         .iter()
         .map(|(a, _)| Term::Var(*a))
         .collect();
@@ -115,39 +163,59 @@ pub fn bench_pebble(shrinked_slp: &SLP, graph: &Graph) -> (Stat, Stat, Stat, Sta
     let multislp = fusion::graph_to_multislp_by_fusion(graph.to_vec(), &targets);
 
     let nr_constants = shrinked_slp.num_of_original_constants();
-
-    let scheduled1 =
-        reorder::deal_multislp(&multislp, nr_constants, targets.clone(), Strategy::UseLRU);
-    let scheduled2 =
-        reorder::deal_multislp(&multislp, nr_constants, targets.clone(), Strategy::UseMRU);
-    let scheduled3 =
-        reorder2::deal_multislp2(&multislp, nr_constants, targets.clone(), Strategy::UseLRU);
-    let scheduled4 = reorder2::deal_multislp2(&multislp, nr_constants, targets, Strategy::UseMRU);
-
-    let schedule_stat1 = stat::analyze(&scheduled1);
-    let schedule_stat2 = stat::analyze(&scheduled2);
-    let schedule_stat3 = stat::analyze(&scheduled3);
-    let schedule_stat4 = stat::analyze(&scheduled4);
-
-    let shrinked_valuation = validation::slp_to_valuation(&shrinked_slp);
-    let renamed = rename(
-        &shrinked_valuation,
-        if cfg!(feature = "dfs_sched") {
-            // dbg!("dfs_sched");
-            &scheduled2
-        } else if cfg!(feature = "bottomup_sched") {
-            // dbg!("bottomup sched");
-            &scheduled4
+    let schedule = |scheduler: Scheduler| -> PebbleProgram {
+        let (strategy, bottomup) = match scheduler {
+            Scheduler::DfsLru => (Strategy::UseLRU, false),
+            Scheduler::DfsMru => (Strategy::UseMRU, false),
+            Scheduler::BottomUpLru => (Strategy::UseLRU, true),
+            Scheduler::BottomUpMru | Scheduler::Auto => (Strategy::UseMRU, true),
+        };
+        if bottomup {
+            reorder2::deal_multislp2(
+                &multislp,
+                nr_constants,
+                targets.clone(),
+                strategy,
+                nr_pebbles,
+            )
         } else {
-            &scheduled2
-        },
-    );
+            reorder::deal_multislp(
+                &multislp,
+                nr_constants,
+                targets.clone(),
+                strategy,
+                nr_pebbles,
+            )
+        }
+    };
+
+    // only Auto tries all the schedulers
+    let (chosen, chosen_stat) = match scheduler {
+        Scheduler::Auto => [
+            Scheduler::DfsLru,
+            Scheduler::DfsMru,
+            Scheduler::BottomUpLru,
+            Scheduler::BottomUpMru,
+        ]
+        .iter()
+        .map(|&s| {
+            let scheduled = schedule(s);
+            let stat = stat::analyze(&scheduled);
+            (s, scheduled, stat)
+        })
+        // ties are broken in favor of the default scheduler
+        .min_by_key(|(s, _, stat)| (stat.nr_page_transfer, *s != Scheduler::default()))
+        .map(|(_, scheduled, stat)| (scheduled, stat))
+        .unwrap(),
+        _ => {
+            let scheduled = schedule(scheduler);
+            let stat = stat::analyze(&scheduled);
+            (scheduled, stat)
+        }
+    };
+
+    let shrinked_valuation = validation::slp_to_valuation(shrinked_slp);
+    let renamed = rename(&shrinked_valuation, &chosen)?;
 
-    (
-        schedule_stat1,
-        schedule_stat2,
-        schedule_stat3,
-        schedule_stat4,
-        renamed,
-    )
+    Ok((chosen_stat, renamed))
 }
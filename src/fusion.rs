@@ -20,14 +20,13 @@ pub fn slp_to_ssa(slp: &[(Term, Term, Term)]) -> Graph {
     // map (`v` in slp) to (l that is the line `l` number defines `v`)
     let mut map: BTreeMap<Term, usize> = BTreeMap::new();
 
-    let mut current_line = 1;
-    for (target, left, right) in slp {
-        let left = map.get_mut(&left).map_or(
+    for (current_line, (target, left, right)) in (1..).zip(slp.iter()) {
+        let left = map.get_mut(left).map_or(
             left.clone(),      // if not found
             |v| Term::Var(*v), // if found
         );
 
-        let right = map.get_mut(&right).map_or(
+        let right = map.get_mut(right).map_or(
             right.clone(),     // if not found
             |v| Term::Var(*v), // if found
         );
@@ -35,7 +34,6 @@ pub fn slp_to_ssa(slp: &[(Term, Term, Term)]) -> Graph {
         ssa.push((Term::Var(current_line), left, right));
 
         map.insert(target.clone(), current_line);
-        current_line += 1;
     }
 
     ssa
@@ -0,0 +1,321 @@
+use crate::bitmatrix::BitMatrix;
+use crate::field::*;
+use crate::fin_field::*;
+use crate::matrix::*;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/*
+ * The section of GF(2^w) for a word size w <= 16 and a primitive polynomial of degree w,
+ * which is given as its coefficient bits, e.g., 0x13 = x^4 + x + 1.
+ *
+ * Unlike GF_2_8, the arithmetic is computed by shift-and-add instead of tables,
+ * since it is used only to build generator matrices and their inverses.
+ * An element e is expanded into the w x w bitmatrix of the multiplication by e,
+ * and a shard of GF(2^w) words into w bit-planes (see run::Program::with_planes).
+ */
+
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct GF_2_W<const W: usize, const PPOLY: u32>(u16);
+
+// x^4 + x + 1 (Jerasure's w = 4)
+#[allow(non_camel_case_types)]
+pub type GF_2_4 = GF_2_W<4, 0x13>;
+
+// x^8 + x^4 + x^3 + x^2 + 1, the same field as GF_2_8
+#[allow(non_camel_case_types)]
+pub type GF_2_8W = GF_2_W<8, 0x11d>;
+
+// x^16 + x^12 + x^3 + x + 1 (Jerasure's w = 16)
+#[allow(non_camel_case_types)]
+pub type GF_2_16 = GF_2_W<16, 0x1100b>;
+
+impl<const W: usize, const PPOLY: u32> GF_2_W<W, PPOLY> {
+    pub fn to_u16(&self) -> u16 {
+        self.0
+    }
+
+    pub fn coef(&self, degree: usize) -> u16 {
+        debug_assert!(degree < W);
+
+        (self.0 >> degree) & 1
+    }
+
+    /// The w x w bitmatrix of the multiplication by `self`,
+    /// whose column `j` is the column vector of `self * x^(w - 1 - j)` (see `bitmatrix::bits_to_colvec`).
+    pub fn to_bitmatrix(&self) -> BitMatrix {
+        let mut bm = BitMatrix::new(W, W);
+        let mut p = *self;
+
+        for deg in 0..W {
+            for i in 0..W {
+                bm[W - 1 - i][W - 1 - deg] = p.coef(i) == 1;
+            }
+            p = p * Self::PRIMITIVE_ELEMENT;
+        }
+
+        bm
+    }
+}
+
+impl<const W: usize, const PPOLY: u32> std::fmt::Display for GF_2_W<W, PPOLY> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#0width$x}", self.0, width = W.div_ceil(4) + 2)
+    }
+}
+
+impl<const W: usize, const PPOLY: u32> From<u16> for GF_2_W<W, PPOLY> {
+    fn from(v: u16) -> Self {
+        assert!((v as u32) < (1 << W), "{} is not in GF(2^{})", v, W);
+
+        GF_2_W(v)
+    }
+}
+
+impl<const W: usize, const PPOLY: u32> From<GF_2_W<W, PPOLY>> for u16 {
+    fn from(v: GF_2_W<W, PPOLY>) -> Self {
+        v.0
+    }
+}
+
+impl<const W: usize, const PPOLY: u32> HasPrimitiveElement for GF_2_W<W, PPOLY> {
+    const PRIMITIVE_ELEMENT: Self = GF_2_W(0b10);
+}
+
+impl<const W: usize, const PPOLY: u32> Add for GF_2_W<W, PPOLY> {
+    type Output = Self;
+
+    // XOR
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, rhs: Self) -> Self {
+        GF_2_W(self.0 ^ rhs.0)
+    }
+}
+
+impl<const W: usize, const PPOLY: u32> Neg for GF_2_W<W, PPOLY> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self
+    }
+}
+
+impl<const W: usize, const PPOLY: u32> Sub for GF_2_W<W, PPOLY> {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, rhs: Self) -> Self {
+        self + (-rhs)
+    }
+}
+
+impl<const W: usize, const PPOLY: u32> Mul for GF_2_W<W, PPOLY> {
+    type Output = Self;
+
+    // a * b = \sum_{b_i = 1} a x^i mod ppoly
+    fn mul(self, rhs: Self) -> Self {
+        let (mut a, mut b, mut r) = (self.0 as u32, rhs.0 as u32, 0u32);
+
+        while b != 0 {
+            if b & 1 == 1 {
+                r ^= a;
+            }
+            b >>= 1;
+            a <<= 1;
+            if (a >> W) & 1 == 1 {
+                a ^= PPOLY;
+            }
+        }
+
+        GF_2_W(r as u16)
+    }
+}
+
+impl<const W: usize, const PPOLY: u32> Div for GF_2_W<W, PPOLY> {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self {
+        self * rhs.mul_inv()
+    }
+}
+
+impl<const W: usize, const PPOLY: u32> Field for GF_2_W<W, PPOLY> {
+    const ZERO: Self = GF_2_W(0);
+    const ONE: Self = GF_2_W(1);
+
+    // x^{-1} = x^{2^w - 2} since x^{2^w - 1} = 1
+    fn mul_inv(&self) -> Self {
+        debug_assert!(*self != Self::ZERO);
+
+        self.exp((1 << W) - 2)
+    }
+}
+
+impl<const W: usize, const PPOLY: u32> FiniteField for GF_2_W<W, PPOLY> {
+    fn enumerate() -> Vec<Self> {
+        (0..1u32 << W).map(|v| GF_2_W(v as u16)).collect()
+    }
+
+    const CARDINALITY: usize = 1 << W;
+
+    // little endian
+    fn from_bytes(v: &[u8]) -> Self {
+        debug_assert!(v.len() == W.div_ceil(8));
+
+        let v = v.iter().rev().fold(0u32, |acc, b| (acc << 8) | *b as u32);
+        (v as u16).into()
+    }
+
+    fn to_byte(&self, idx: usize) -> u8 {
+        debug_assert!(idx < W.div_ceil(8));
+
+        (self.0 >> (8 * idx)) as u8
+    }
+}
+
+/// `rsv_bitmatrix::matrix_to_bitmatrix` for GF(2^w): every entry is expanded into its w x w bitmatrix.
+pub fn matrix_to_bitmatrix<const W: usize, const PPOLY: u32>(
+    m: &Matrix<GF_2_W<W, PPOLY>>,
+) -> BitMatrix {
+    let mut bm = BitMatrix::new(m.height() * W, m.width() * W);
+
+    for i in 0..m.height() {
+        for j in 0..m.width() {
+            let block = m[i][j].to_bitmatrix();
+            for r in 0..W {
+                for c in 0..W {
+                    bm[i * W + r][j * W + c] = block[r][c];
+                }
+            }
+        }
+    }
+
+    bm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmatrix::{bits_to_colvec, colvec_to_bits};
+    use crate::pipeline::{self, Options};
+    use crate::rsv_bitmatrix;
+    use crate::vandermonde;
+    use itertools::Itertools;
+
+    fn check_field<const W: usize, const PPOLY: u32>(elems: &[GF_2_W<W, PPOLY>]) {
+        for a in elems {
+            if *a != GF_2_W::ZERO {
+                assert_eq!(*a * a.mul_inv(), GF_2_W::ONE);
+            }
+            for b in elems {
+                assert_eq!(*a * *b, *b * *a);
+                // the bitmatrix of a multiplies the column vector of b into that of a * b
+                let ab = a.to_bitmatrix().mul(&bits_to_colvec(b.to_u16() as u32, W));
+                assert_eq!(colvec_to_bits(&ab, W), (*a * *b).to_u16() as u32);
+            }
+        }
+
+        // the primitive element generates the multiplicative group
+        let alpha = GF_2_W::<W, PPOLY>::PRIMITIVE_ELEMENT;
+        let order = (1..=(1u32 << W) - 1)
+            .find(|e| alpha.exp(*e) == GF_2_W::ONE)
+            .unwrap();
+        assert_eq!(order, (1 << W) - 1);
+    }
+
+    #[test]
+    fn field_test() {
+        check_field(&GF_2_4::enumerate());
+        check_field(&GF_2_8W::enumerate());
+        let sample: Vec<GF_2_16> = (0u16..64).map(|v| (v.wrapping_mul(1031)).into()).collect();
+        check_field(&sample);
+
+        // GF_2_8W is GF_2_8
+        for (a, b) in (0u8..=255).cartesian_product(0u8..=255) {
+            let (a8, b8) = (GF_2_8::from(a), GF_2_8::from(b));
+            let (aw, bw) = (GF_2_8W::from(a as u16), GF_2_8W::from(b as u16));
+            assert_eq!(u8::from(a8 * b8) as u16, (aw * bw).to_u16());
+        }
+
+        assert_eq!(GF_2_16::from_bytes(&[0x34, 0x12]).to_u16(), 0x1234);
+        assert_eq!(GF_2_16::from(0x1234).to_byte(1), 0x12);
+        assert_eq!(GF_2_4::from(0xa).to_string(), "0xa");
+    }
+
+    #[test]
+    fn matrix_to_bitmatrix_test() {
+        let m = vandermonde::isa_rsv(6, 3);
+        let mut mw: Matrix<GF_2_8W> = Matrix::new(m.size());
+        for i in 0..m.height() {
+            for j in 0..m.width() {
+                mw[i][j] = (u8::from(m[i][j]) as u16).into();
+            }
+        }
+        assert!(matrix_to_bitmatrix(&mw) == rsv_bitmatrix::matrix_to_bitmatrix(&m));
+
+        let m: Matrix<GF_2_4> = vandermonde::rsv_over(4, 2).unwrap();
+        let bm = matrix_to_bitmatrix(&m);
+        assert_eq!((bm.height(), bm.width()), (6 * 4, 4 * 4));
+    }
+
+    // the w-bit word at `idx` of a shard of w bit-planes, whose first plane holds the top bits
+    fn word(shard: &[u8], w: usize, idx: usize) -> u16 {
+        let width = shard.len() / w;
+        (0..w).fold(0, |acc, plane| {
+            (acc << 1) | ((shard[plane * width + idx / 8] >> (7 - idx % 8)) & 1) as u16
+        })
+    }
+
+    #[test]
+    fn gf_2_4_run_test() {
+        let (n, p) = (4, 2);
+        let m: Matrix<GF_2_4> = vandermonde::rsv_over(n, p).unwrap();
+        let mut parity = m.clone();
+        parity.drop_rows((0..n).collect());
+
+        let program =
+            pipeline::compile_bitmatrix(&matrix_to_bitmatrix(&parity), &Options::default())
+                .unwrap()
+                .with_planes(4)
+                .unwrap();
+        assert!(program.clone().with_planes(3).is_err());
+
+        let len = 4 * 64 * 3;
+        let data: Vec<Vec<u8>> = (0..n)
+            .map(|i| (0..len).map(|j| (i * 37 + j * 11 + j / 7) as u8).collect())
+            .collect();
+        let mut outputs = vec![vec![0u8; len]; p];
+        {
+            let inputs: Vec<&[u8]> = data.iter().map(|s| &s[..]).collect();
+            let mut outs: Vec<&mut [u8]> = outputs.iter_mut().map(|s| &mut s[..]).collect();
+            program.run_on_shards(&inputs, &mut outs).unwrap();
+        }
+
+        for idx in 0..len / 4 * 8 {
+            for (r, out) in outputs.iter().enumerate() {
+                let expected = (0..n).fold(GF_2_4::ZERO, |acc, c| {
+                    acc + parity[r][c] * GF_2_4::from(word(&data[c], 4, idx))
+                });
+                assert_eq!(word(out, 4, idx), expected.to_u16());
+            }
+        }
+
+        // decode data blocks 0 and 2 from the others
+        let mut sub = m.clone();
+        sub.drop_rows(vec![0, 2]);
+        let inv = sub.inverse().unwrap();
+        let mut rows = inv.clone();
+        rows.drop_rows(vec![1, 3]);
+        let decoder = pipeline::compile_bitmatrix(&matrix_to_bitmatrix(&rows), &Options::default())
+            .unwrap()
+            .with_planes(4)
+            .unwrap();
+
+        let mut recovered = vec![vec![0u8; len]; 2];
+        let available = [&data[1][..], &data[3][..], &outputs[0][..], &outputs[1][..]];
+        let mut outs: Vec<&mut [u8]> = recovered.iter_mut().map(|s| &mut s[..]).collect();
+        decoder.run_on_shards(&available, &mut outs).unwrap();
+        assert!(recovered[0] == data[0] && recovered[1] == data[2]);
+    }
+}
@@ -0,0 +1,141 @@
+use clap::arg_enum;
+
+arg_enum! {
+    // the instruction set used by the XOR kernels
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum Kernel {
+        Avx2,
+        Sse2,
+        Portable, // 64-bit words
+    }
+}
+
+/// `dst = vs[0] ^ vs[1] ^ ...` for `len` bytes, where `vs` has at least two sources
+/// and `len` is a multiple of 64.
+pub type XorFn = unsafe fn(*mut u8, &[*const u8], usize);
+
+/// Whether `vs[0] ^ vs[1] ^ ...` is zero for `len` bytes, where `vs` has at least one source
+/// and `len` is a multiple of 64.
+pub type XorIsZeroFn = unsafe fn(&[*const u8], usize) -> bool;
+
+impl Kernel {
+    /// The fastest kernel set which the running CPU supports
+    pub fn detect() -> Self {
+        if Kernel::Avx2.is_available() {
+            Kernel::Avx2
+        } else if Kernel::Sse2.is_available() {
+            Kernel::Sse2
+        } else {
+            Kernel::Portable
+        }
+    }
+
+    pub fn is_available(self) -> bool {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse2 => is_x86_feature_detected!("sse2"),
+            Kernel::Portable => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    /// The caller has to make sure that `self.is_available()`.
+    pub fn xor_fn(self) -> XorFn {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => avx2_xor_block,
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse2 => crate::xor_sse2::sse2_page_xor,
+            #[allow(unreachable_patterns)]
+            _ => crate::xor_portable::portable_page_xor,
+        }
+    }
+
+    /// The caller has to make sure that `self.is_available()`.
+    pub fn xor_is_zero_fn(self) -> XorIsZeroFn {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => crate::xor::avx2_page_xor_is_zero,
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse2 => crate::xor_sse2::sse2_page_xor_is_zero,
+            #[allow(unreachable_patterns)]
+            _ => crate::xor_portable::portable_page_xor_is_zero,
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+unsafe fn avx2_xor_block(dst: *mut u8, vs: &[*const u8], len: usize) {
+    // the 128-byte kernels process the body and the 64-byte ones the rest
+    let body = len - len % 128;
+    if body > 0 {
+        crate::xor::avx2_page_xor(dst, vs, body);
+    }
+    if body == 0 {
+        crate::xor64::avx2_page_xor(dst, vs);
+    } else if body < len {
+        let rest: Vec<*const u8> = vs.iter().map(|v| v.add(body)).collect();
+        crate::xor64::avx2_page_xor(dst.add(body), &rest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::PageAlignedArray;
+    use crate::*;
+
+    #[test]
+    fn kernels_test() {
+        assert!(Kernel::detect().is_available());
+        assert!(Kernel::Portable.is_available());
+
+        for kernel in Kernel::variants().iter() {
+            let kernel: Kernel = kernel.parse().unwrap();
+            if !kernel.is_available() {
+                continue;
+            }
+            let xor = kernel.xor_fn();
+
+            for len in [64, 192, 2048] {
+                for arity in 2..=17 {
+                    let mut srcs = PageAlignedArray::new(len * arity).unwrap();
+                    fill_by_random(srcs.as_mut_slice());
+                    let mut dst = PageAlignedArray::new(len).unwrap();
+
+                    let mut expected = vec![0u8; len];
+                    for src in srcs.split(arity) {
+                        for (x, y) in expected.iter_mut().zip(src) {
+                            *x ^= y;
+                        }
+                    }
+
+                    let vs: Vec<*const u8> = srcs.split(arity).iter().map(|s| s.as_ptr()).collect();
+                    unsafe { xor(dst.as_mut_slice().as_mut_ptr(), &vs, len) };
+                    assert!(
+                        dst.as_slice() == &expected[..],
+                        "{:?} {} {}",
+                        kernel,
+                        len,
+                        arity
+                    );
+
+                    let is_zero = kernel.xor_is_zero_fn();
+                    let vs: Vec<*const u8> = std::iter::once(dst.head()).chain(vs).collect();
+                    assert!(unsafe { is_zero(&vs, len) });
+                    assert!(!unsafe { is_zero(&vs[1..], len) } || expected.iter().all(|b| *b == 0));
+
+                    // the destination may be one of the sources
+                    unsafe { xor(dst.as_mut_slice().as_mut_ptr(), &vs, len) };
+                    assert!(dst.as_slice().iter().all(|b| *b == 0));
+                    assert!(unsafe { is_zero(&vs[..1], len) });
+                    dst.as_mut_slice()[len - 1] = 1;
+                    assert!(!unsafe { is_zero(&vs, len) });
+                }
+            }
+        }
+    }
+}
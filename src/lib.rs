@@ -6,29 +6,49 @@ extern crate itertools;
 extern crate libc;
 
 pub mod bitmatrix;
+pub mod bundle;
+pub mod cauchy;
 pub mod comparison;
+pub mod correction;
+pub mod decoder;
+pub mod encoder;
+pub mod error;
 pub mod fast_repair;
 pub mod field;
+pub mod files;
 pub mod fin_field;
 pub mod for_benchmark;
 pub mod fusion;
+pub mod gf2w;
+pub mod kernel;
+pub mod lrc;
 pub mod matrix;
 pub mod optimize_slp;
+pub mod pipeline;
+pub mod program_file;
 pub mod renaming;
 pub mod reorder;
 pub mod reorder2;
 pub mod repair;
+pub mod report;
 pub mod rsv_bitmatrix;
 pub mod run;
+pub mod shard_file;
 pub mod slp;
 pub mod stat;
+pub mod stream;
 pub mod univariate_polynomial;
 pub mod validation;
 pub mod vandermonde;
 pub mod vecteur;
+#[cfg(target_arch = "x86_64")]
 pub mod xor;
+#[cfg(target_arch = "x86_64")]
 pub mod xor64;
+pub mod xor_portable;
 pub mod xor_repair;
+#[cfg(target_arch = "x86_64")]
+pub mod xor_sse2;
 
 pub const BLOCK_SIZE_PER_ITER: usize = if cfg!(feature = "4096block") {
     4096
@@ -52,7 +72,8 @@ pub const BLOCK_SIZE_PER_ITER: usize = if cfg!(feature = "4096block") {
     // default
     2048
 };
-pub const PEBBLE_NUM: usize = (32 * 1024) / BLOCK_SIZE_PER_ITER;
+pub const L1_CACHE_SIZE: usize = 32 * 1024;
+pub const PEBBLE_NUM: usize = L1_CACHE_SIZE / BLOCK_SIZE_PER_ITER;
 
 #[derive(Eq, PartialEq, Clone, Copy, Debug)]
 pub struct Parameter {
@@ -60,6 +81,25 @@ pub struct Parameter {
     pub nr_parity_block: usize,
 }
 
+impl Parameter {
+    // GF(2^8) can not distinguish more than 256 blocks
+    pub fn check(&self) -> Result<(), error::XorSlpError> {
+        if self.nr_data_block == 0 || self.nr_parity_block == 0 {
+            return Err(error::XorSlpError::InvalidParameter(format!(
+                "{:?}: both data and parity blocks are required",
+                self
+            )));
+        }
+        if self.nr_data_block + self.nr_parity_block > 256 {
+            return Err(error::XorSlpError::InvalidParameter(format!(
+                "{:?}: too many blocks",
+                self
+            )));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug)]
 pub enum Term {
     Cst(usize),
@@ -119,7 +159,7 @@ pub fn dump_valuation(valuation: &Valuation) {
         let mut s: String = String::new();
         let mut iter = val.iter();
         s.push_str(&format!("{}", iter.next().unwrap().cst_to_usize().unwrap()));
-        while let Some(v) = iter.next() {
+        for v in iter {
             s.push_str(&format!(" + {}", v.cst_to_usize().unwrap()));
         }
         println!("{} = {}", t.var_to_usize().unwrap(), s);
@@ -159,11 +199,14 @@ pub fn multislp_to_multiterm_slp(g: &MultiSLP) -> Vec<(Term, Vec<Term>)> {
 }
 
 pub fn gen_data(len: usize) -> Vec<u8> {
+    // keep misaligned buffers alive so that the allocator hands out a fresh address
+    let mut misaligned = Vec::new();
     let mut v: Vec<u8> = loop {
         let v = vec![0u8; len];
-        if v.as_ptr() as usize % 32 == 0 {
+        if (v.as_ptr() as usize).is_multiple_of(32) {
             break v;
         }
+        misaligned.push(v);
     };
 
     for ptr in v.iter_mut() {
@@ -180,7 +223,7 @@ pub fn fill_by_random(ary: &mut [u8]) {
 }
 
 pub fn split(n: usize, data: Vec<u8>) -> Vec<Vec<u8>> {
-    assert!(data.len() % n == 0);
+    assert!(data.len().is_multiple_of(n));
 
     let width = data.len() / n;
     let mut matrix: Vec<Vec<u8>> = Vec::new();
@@ -205,13 +248,18 @@ pub fn drop<T>(vv: Vec<T>, remove: &[usize]) -> Vec<T> {
 }
 
 pub fn drop8<T>(vv: Vec<T>, remove: &[usize]) -> Vec<T> {
+    drop_planes(vv, remove, 8)
+}
+
+/// Remove the `w` entries of every block in `remove` from the entries of `w` bit-planes per block.
+pub fn drop_planes<T>(vv: Vec<T>, remove: &[usize], w: usize) -> Vec<T> {
     let mut vv = vv;
     let mut remove = remove.to_vec();
     remove.sort_by(|a, b| b.cmp(a)); // v[0] > v[1] > ...
 
     for r in remove {
-        for _ in 0..8 {
-            vv.remove(r * 8);
+        for _ in 0..w {
+            vv.remove(r * w);
         }
     }
 
@@ -219,6 +267,7 @@ pub fn drop8<T>(vv: Vec<T>, remove: &[usize]) -> Vec<T> {
 }
 
 #[cfg(test)]
+#[allow(clippy::zero_prefixed_literal)]
 mod test {
     use crate::*;
 
@@ -235,7 +284,7 @@ mod test {
     pub fn drop_test1() {
         let v = vec![0, 1, 2, 3, 4, 5];
 
-        assert_eq!(drop(v, &vec![3, 1]), vec![0, 2, 4, 5]);
+        assert_eq!(drop(v, &[3, 1]), vec![0, 2, 4, 5]);
     }
 
     #[test]
@@ -246,7 +295,7 @@ mod test {
         ];
 
         assert_eq!(
-            drop8(v, &vec![1]),
+            drop8(v, &[1]),
             vec![01, 02, 03, 04, 05, 06, 07, 08, 21, 22, 23, 24, 25, 26, 27, 28]
         );
     }
@@ -259,7 +308,7 @@ mod test {
         ];
 
         assert_eq!(
-            drop8(v, &vec![1, 3]),
+            drop8(v, &[1, 3]),
             vec![
                 01, 02, 03, 04, 05, 06, 07, 08, 21, 22, 23, 24, 25, 26, 27, 28, 41, 42, 43, 44, 45,
                 46, 47, 48
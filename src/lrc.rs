@@ -0,0 +1,515 @@
+use crate::decoder::{ProgramCache, DEFAULT_CACHE_CAPACITY};
+use crate::error::XorSlpError;
+use crate::field::*;
+use crate::fin_field::GF_2_8;
+use crate::matrix::*;
+use crate::pipeline::{self, Options};
+use crate::run::{Program, RunError};
+use crate::{rsv_bitmatrix, vandermonde};
+use std::sync::{Arc, Mutex};
+
+/*
+ * Locally repairable codes LRC(k, l, r) as in Windows Azure Storage:
+ * the k data shards are split into l local groups of contiguous shards,
+ * each group has a local parity which is the XOR of its data shards,
+ * and there are r global parities over all the data shards.
+ *
+ * The shards are [data_0, ..., data_{k-1}, local_0, ..., local_{l-1}, global_0, ..., global_{r-1}].
+ * The global parities are the rows of isa_rsv(k, r + 1) except for its first parity row,
+ * which is the XOR of all the data shards, i.e., the XOR of the local parities.
+ *
+ * A shard which is the only missing one of its group (the data shards and the local parity)
+ * is repaired locally by the XOR of the other shards of the group.
+ * The other missing shards are recovered from k independent available shards.
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LrcParameter {
+    pub nr_data_block: usize,
+    pub nr_local_group: usize,
+    pub nr_global_parity: usize,
+}
+
+impl LrcParameter {
+    pub fn check(&self) -> Result<(), XorSlpError> {
+        if self.nr_data_block == 0 || self.nr_local_group == 0 {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "{:?}: both data blocks and local groups are required",
+                self
+            )));
+        }
+        if self.nr_local_group > self.nr_data_block {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "{:?}: more local groups than data blocks",
+                self
+            )));
+        }
+        if self.nr_data_block + self.nr_global_parity + 1 > 256 {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "{:?}: too many blocks",
+                self
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn nr_parity_block(&self) -> usize {
+        self.nr_local_group + self.nr_global_parity
+    }
+
+    pub fn nr_shards(&self) -> usize {
+        self.nr_data_block + self.nr_parity_block()
+    }
+
+    /// The group of the data shard or the local parity `shard`, or `None` for a global parity.
+    pub fn group(&self, shard: usize) -> Option<usize> {
+        if shard < self.nr_data_block {
+            Some(shard * self.nr_local_group / self.nr_data_block)
+        } else if shard < self.nr_data_block + self.nr_local_group {
+            Some(shard - self.nr_data_block)
+        } else {
+            None
+        }
+    }
+
+    /// The data shards of the group `g` followed by its local parity
+    pub fn group_shards(&self, g: usize) -> Vec<usize> {
+        let mut shards: Vec<usize> = (0..self.nr_data_block)
+            .filter(|&i| self.group(i) == Some(g))
+            .collect();
+        shards.push(self.nr_data_block + g);
+        shards
+    }
+}
+
+/// The (k + l + r) x k generator matrix of `parameter`
+pub fn lrc(parameter: LrcParameter) -> Result<Matrix<GF_2_8>, XorSlpError> {
+    parameter.check()?;
+    let (k, l, r) = (
+        parameter.nr_data_block,
+        parameter.nr_local_group,
+        parameter.nr_global_parity,
+    );
+
+    let rsv = vandermonde::isa_rsv(k, r + 1);
+    let mut m = Matrix::new(MatrixSize {
+        height: k + l + r,
+        width: k,
+    });
+
+    for i in 0..k {
+        m[i][i] = GF_2_8::ONE;
+        m[k + parameter.group(i).unwrap()][i] = GF_2_8::ONE;
+    }
+    for i in 0..r {
+        for j in 0..k {
+            m[k + l + i][j] = rsv[k + 1 + i][j];
+        }
+    }
+
+    Ok(m)
+}
+
+// the rows `ixs` of `m` in order
+fn select_rows(m: &Matrix<GF_2_8>, ixs: &[usize]) -> Matrix<GF_2_8> {
+    let mut sub = Matrix::new(MatrixSize {
+        height: ixs.len(),
+        width: m.width(),
+    });
+    for (i, &ix) in ixs.iter().enumerate() {
+        for j in 0..m.width() {
+            sub[i][j] = m[ix][j];
+        }
+    }
+    sub
+}
+
+/// The first rows of `candidates` which are linearly independent, at most `m.width()` of them.
+pub fn independent_rows(m: &Matrix<GF_2_8>, candidates: &[usize]) -> Vec<usize> {
+    // reduced rows with their pivot columns
+    let mut basis: Vec<(usize, Vec<GF_2_8>)> = Vec::new();
+    let mut rows = Vec::new();
+
+    for &c in candidates {
+        if rows.len() == m.width() {
+            break;
+        }
+        let mut v: Vec<GF_2_8> = (0..m.width()).map(|j| m[c][j]).collect();
+        for (pivot, b) in &basis {
+            let f = v[*pivot];
+            if f != GF_2_8::ZERO {
+                for j in 0..v.len() {
+                    v[j] = v[j] - f * b[j];
+                }
+            }
+        }
+        if let Some(pivot) = v.iter().position(|e| *e != GF_2_8::ZERO) {
+            let inv = v[pivot].mul_inv();
+            let v: Vec<GF_2_8> = v.iter().map(|e| *e * inv).collect();
+            basis.push((pivot, v));
+            rows.push(c);
+        }
+    }
+
+    rows
+}
+
+/// A step of the recovery of missing shards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Repair {
+    /// `target` is the XOR of the other shards `inputs` of its local group.
+    Local { target: usize, inputs: Vec<usize> },
+    /// `targets` are recovered from the k independent shards `inputs`.
+    Global {
+        targets: Vec<usize>,
+        inputs: Vec<usize>,
+    },
+}
+
+impl Repair {
+    pub fn inputs(&self) -> &[usize] {
+        match self {
+            Repair::Local { inputs, .. } => inputs,
+            Repair::Global { inputs, .. } => inputs,
+        }
+    }
+
+    pub fn targets(&self) -> Vec<usize> {
+        match self {
+            Repair::Local { target, .. } => vec![*target],
+            Repair::Global { targets, .. } => targets.clone(),
+        }
+    }
+}
+
+/// The steps recovering the `erased` shards (sorted), local repairs first.
+///
+/// Returns `XorSlpError::NonInvertible` if the available shards do not determine the data.
+pub fn plan(
+    parameter: LrcParameter,
+    generator: &Matrix<GF_2_8>,
+    erased: &[usize],
+) -> Result<Vec<Repair>, XorSlpError> {
+    let nr_shards = parameter.nr_shards();
+    if let Some(e) = erased.iter().find(|&&e| e >= nr_shards) {
+        return Err(XorSlpError::InvalidParameter(format!(
+            "block {} does not exist",
+            e
+        )));
+    }
+
+    let mut steps = Vec::new();
+    let mut missing: Vec<usize> = erased.to_vec();
+
+    for g in 0..parameter.nr_local_group {
+        let group = parameter.group_shards(g);
+        let lost: Vec<usize> = group
+            .iter()
+            .cloned()
+            .filter(|i| erased.contains(i))
+            .collect();
+        if let [target] = lost[..] {
+            let inputs = group.into_iter().filter(|&i| i != target).collect();
+            steps.push(Repair::Local { target, inputs });
+            missing.retain(|&i| i != target);
+        }
+    }
+
+    if !missing.is_empty() {
+        // the locally repaired shards are available as well
+        let available: Vec<usize> = (0..nr_shards).filter(|i| !missing.contains(i)).collect();
+        let inputs = independent_rows(generator, &available);
+        if inputs.len() < parameter.nr_data_block {
+            return Err(XorSlpError::NonInvertible(erased.to_vec()));
+        }
+        steps.push(Repair::Global {
+            targets: missing,
+            inputs,
+        });
+    }
+
+    Ok(steps)
+}
+
+/// Whether the `erased` shards can be recovered
+pub fn is_decodable(parameter: LrcParameter, generator: &Matrix<GF_2_8>, erased: &[usize]) -> bool {
+    plan(parameter, generator, erased).is_ok()
+}
+
+// the matrix computing the shards `targets` from the shards `inputs`
+fn repair_matrix(
+    generator: &Matrix<GF_2_8>,
+    targets: &[usize],
+    inputs: &[usize],
+) -> Result<Matrix<GF_2_8>, XorSlpError> {
+    let inv = select_rows(generator, inputs)
+        .inverse()
+        .ok_or_else(|| XorSlpError::NonInvertible(targets.to_vec()))?;
+    Ok(&select_rows(generator, targets) * &inv)
+}
+
+pub struct LrcEncoder {
+    parameter: LrcParameter,
+    matrix: Matrix<GF_2_8>,
+    program: Program,
+}
+
+impl LrcEncoder {
+    /// Build the optimized program computing the local and global parities.
+    pub fn new(parameter: LrcParameter, options: Options) -> Result<Self, XorSlpError> {
+        let matrix = lrc(parameter)?;
+
+        let mut parity = matrix.clone();
+        parity.drop_rows((0..parameter.nr_data_block).collect());
+        let bitmatrix = rsv_bitmatrix::matrix_to_bitmatrix(&parity);
+        let program = pipeline::compile_bitmatrix(&bitmatrix, &options)?;
+
+        Ok(LrcEncoder {
+            parameter,
+            matrix,
+            program,
+        })
+    }
+
+    pub fn parameter(&self) -> LrcParameter {
+        self.parameter
+    }
+
+    /// The (k + l + r) x k generator matrix
+    pub fn matrix(&self) -> &Matrix<GF_2_8> {
+        &self.matrix
+    }
+
+    /// Compute the local parities followed by the global ones from `data_shards`.
+    ///
+    /// All the shards must have the same length, which is a multiple of 8.
+    pub fn encode(
+        &self,
+        data_shards: &[&[u8]],
+        parity_shards: &mut [&mut [u8]],
+    ) -> Result<(), XorSlpError> {
+        if data_shards.len() != self.parameter.nr_data_block {
+            return Err(RunError::WrongNumberOfInputs {
+                expected: self.parameter.nr_data_block,
+                actual: data_shards.len(),
+            }
+            .into());
+        }
+        if parity_shards.len() != self.parameter.nr_parity_block() {
+            return Err(RunError::WrongNumberOfOutputs {
+                expected: self.parameter.nr_parity_block(),
+                actual: parity_shards.len(),
+            }
+            .into());
+        }
+
+        Ok(self.program.run_on_shards(data_shards, parity_shards)?)
+    }
+}
+
+pub struct LrcDecoder {
+    parameter: LrcParameter,
+    options: Options,
+    matrix: Matrix<GF_2_8>,
+    // compiled on demand, keyed by (targets, inputs) of a repair
+    programs: Mutex<ProgramCache>,
+}
+
+impl LrcDecoder {
+    pub fn new(parameter: LrcParameter, options: Options) -> Result<Self, XorSlpError> {
+        Self::with_capacity(parameter, options, DEFAULT_CACHE_CAPACITY)
+    }
+
+    /// `capacity` is the maximum number of repair programs which are kept.
+    pub fn with_capacity(
+        parameter: LrcParameter,
+        options: Options,
+        capacity: usize,
+    ) -> Result<Self, XorSlpError> {
+        Ok(LrcDecoder {
+            parameter,
+            options,
+            matrix: lrc(parameter)?,
+            programs: Mutex::new(ProgramCache::new(capacity)),
+        })
+    }
+
+    pub fn parameter(&self) -> LrcParameter {
+        self.parameter
+    }
+
+    /// The number of programs currently cached
+    pub fn nr_cached(&self) -> usize {
+        self.programs.lock().unwrap().len()
+    }
+
+    /// See `lrc::plan`
+    pub fn plan(&self, erased: &[usize]) -> Result<Vec<Repair>, XorSlpError> {
+        plan(self.parameter, &self.matrix, erased)
+    }
+
+    /// The program of a step of `plan`.
+    ///
+    /// The inputs of the program are the shards `repair.inputs()`,
+    /// and the outputs are the shards `repair.targets()`, both in ascending order.
+    pub fn program(&self, repair: &Repair) -> Result<Arc<Program>, XorSlpError> {
+        let key = (repair.targets(), repair.inputs().to_vec());
+        if let Some(program) = self.programs.lock().unwrap().get(&key) {
+            return Ok(program);
+        }
+
+        let m = match repair {
+            Repair::Local { inputs, .. } => {
+                let mut xor = Matrix::new(MatrixSize {
+                    height: 1,
+                    width: inputs.len(),
+                });
+                for j in 0..inputs.len() {
+                    xor[0][j] = GF_2_8::ONE;
+                }
+                xor
+            }
+            Repair::Global { targets, inputs } => repair_matrix(&self.matrix, targets, inputs)?,
+        };
+        let bitmatrix = rsv_bitmatrix::matrix_to_bitmatrix(&m);
+        let program = Arc::new(pipeline::compile_bitmatrix(&bitmatrix, &self.options)?);
+        self.programs.lock().unwrap().insert(key, program.clone());
+        Ok(program)
+    }
+
+    /// Recover the shards `i` with `!present[i]` in place.
+    ///
+    /// `shards` consists of the data shards, the local parities and the global parities.
+    /// As in `LrcEncoder::encode`, the shards must have the same length, which is a multiple of 8.
+    pub fn reconstruct(
+        &self,
+        shards: &mut [&mut [u8]],
+        present: &[bool],
+    ) -> Result<(), XorSlpError> {
+        let nr_shards = self.parameter.nr_shards();
+        if shards.len() != nr_shards {
+            return Err(RunError::WrongNumberOfInputs {
+                expected: nr_shards,
+                actual: shards.len(),
+            }
+            .into());
+        }
+        if present.len() != nr_shards {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "the mask has {} entries for {} shards",
+                present.len(),
+                nr_shards
+            )));
+        }
+
+        let erased: Vec<usize> = (0..nr_shards).filter(|&i| !present[i]).collect();
+        for repair in self.plan(&erased)? {
+            let program = self.program(&repair)?;
+            let targets = repair.targets();
+
+            let mut inputs: Vec<&[u8]> = Vec::new();
+            let mut outputs: Vec<&mut [u8]> = Vec::new();
+            for (i, shard) in shards.iter_mut().enumerate() {
+                if repair.inputs().contains(&i) {
+                    inputs.push(&**shard);
+                } else if targets.contains(&i) {
+                    outputs.push(&mut **shard);
+                }
+            }
+            program.run_on_shards(&inputs, &mut outputs)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::OptimizeLevel;
+    use crate::*;
+    use itertools::Itertools;
+
+    const PARAM: LrcParameter = LrcParameter {
+        nr_data_block: 6,
+        nr_local_group: 2,
+        nr_global_parity: 2,
+    };
+
+    #[test]
+    fn plan_test() {
+        let m = lrc(PARAM).unwrap();
+        assert_eq!(PARAM.group_shards(0), vec![0, 1, 2, 6]);
+        assert_eq!(PARAM.group_shards(1), vec![3, 4, 5, 7]);
+        assert_eq!(PARAM.group(8), None);
+
+        // a single missing shard of a group is read from the group only
+        assert_eq!(
+            plan(PARAM, &m, &[4]).unwrap(),
+            vec![Repair::Local {
+                target: 4,
+                inputs: vec![3, 5, 7]
+            }]
+        );
+        let steps = plan(PARAM, &m, &[1, 5, 8]).unwrap();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[2].targets(), vec![8]);
+        assert_eq!(steps[2].inputs(), &[0, 1, 2, 3, 4, 5]);
+
+        // like LRC(12, 2, 2) of Azure, any 3 missing shards are recoverable
+        assert!((0..10).combinations(3).all(|e| is_decodable(PARAM, &m, &e)));
+        // and so are the 4 missing shards of 2 + 2 in the groups, but not 4 of a group
+        assert!(is_decodable(PARAM, &m, &[0, 1, 3, 4]));
+        assert!(!is_decodable(PARAM, &m, &[0, 1, 2, 6]));
+        assert_eq!(
+            plan(PARAM, &m, &[0, 1, 2, 6]),
+            Err(XorSlpError::NonInvertible(vec![0, 1, 2, 6]))
+        );
+    }
+
+    #[test]
+    fn lrc_round_trip_test() {
+        let options = Options {
+            level: OptimizeLevel::Nooptim,
+            ..Options::default()
+        };
+        let encoder = LrcEncoder::new(PARAM, options).unwrap();
+        // fewer programs than the patterns below need are kept
+        let decoder = LrcDecoder::with_capacity(PARAM, options, 2).unwrap();
+
+        let len = 8 * 64 * 3;
+        let mut original = vec![0u8; len * 10];
+        fill_by_random(&mut original[..len * 6]);
+        {
+            let (data, parity) = original.split_at_mut(len * 6);
+            let data_shards: Vec<&[u8]> = data.chunks(len).collect();
+            let mut parity_shards: Vec<&mut [u8]> = parity.chunks_mut(len).collect();
+            encoder.encode(&data_shards, &mut parity_shards).unwrap();
+        }
+
+        // the local parities are the XORs of the groups
+        for (i, b) in original[len * 6..len * 7].iter().enumerate() {
+            assert_eq!(*b, original[i] ^ original[len + i] ^ original[len * 2 + i]);
+        }
+
+        for erased in [
+            vec![2],
+            vec![7],
+            vec![9],
+            vec![0, 4],
+            vec![1, 2, 9],
+            vec![0, 3, 5, 6],
+        ] {
+            let mut shards = original.clone();
+            for &e in &erased {
+                shards[len * e..len * (e + 1)]
+                    .iter_mut()
+                    .for_each(|b| *b = 0);
+            }
+            let present: Vec<bool> = (0..10).map(|i| !erased.contains(&i)).collect();
+            let mut chunks: Vec<&mut [u8]> = shards.chunks_mut(len).collect();
+            decoder.reconstruct(&mut chunks, &present).unwrap();
+            assert!(shards == original, "{:?}", erased);
+        }
+        assert_eq!(decoder.nr_cached(), 2);
+    }
+}
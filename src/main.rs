@@ -1,11 +1,21 @@
+use xorslp_ec::bundle;
+use xorslp_ec::comparison::{self, OutputFormat, StatWriter};
+use xorslp_ec::decoder;
+use xorslp_ec::encoder;
+use xorslp_ec::error::XorSlpError;
+use xorslp_ec::files;
 use xorslp_ec::for_benchmark;
+use xorslp_ec::kernel::Kernel;
+use xorslp_ec::pipeline::{self, OptimizeLevel, Options, Scheduler};
 use xorslp_ec::reorder::Pebble;
+use xorslp_ec::report;
 use xorslp_ec::rsv_bitmatrix;
 use xorslp_ec::run;
 use xorslp_ec::slp;
 use xorslp_ec::vandermonde;
 use xorslp_ec::Parameter;
 
+use std::path::PathBuf;
 use std::time::Instant;
 
 extern crate itertools;
@@ -14,17 +24,6 @@ use itertools::Itertools;
 extern crate structopt;
 use structopt::StructOpt;
 
-extern crate clap;
-use clap::arg_enum;
-arg_enum! {
-    #[derive(Debug, Copy, Clone)]
-    enum OptimizeLevel {
-        Nooptim,
-        Fusion,
-        FusionSchedule,
-    }
-}
-
 #[derive(StructOpt, Debug)]
 struct Opt {
     #[structopt(long)]
@@ -64,6 +63,61 @@ struct Opt {
 
     #[structopt(long)]
     stat_sec75: bool,
+
+    #[structopt(long)]
+    block_size: Option<usize>, // default BLOCK_SIZE_PER_ITER
+
+    #[structopt(long,
+                possible_values = &Scheduler::variants(),
+                case_insensitive = true)]
+    // default DfsMru (BottomUpMru with the bottomup_sched feature); BottomUpMru for the statistics
+    scheduler: Option<Scheduler>,
+
+    #[structopt(long,
+                possible_values = &Kernel::variants(),
+                case_insensitive = true)]
+    kernel: Option<Kernel>, // default the fastest one the CPU supports
+
+    #[structopt(long)]
+    threads: Option<Vec<usize>>, // numbers of worker threads to report the scaling for
+
+    #[structopt(long,
+                possible_values = &OutputFormat::variants(),
+                case_insensitive = true, default_value="Text")]
+    format: OutputFormat, // of the statistics
+
+    #[structopt(long, parse(from_os_str))]
+    bundle: Option<PathBuf>, // write the decoding programs of all the erasure patterns
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+// the shards of <prefix> are <prefix>.0, <prefix>.1, ... (see files.rs)
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Split a file into --data-block data shards and --parity-block parity shards
+    Encode {
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+        #[structopt(parse(from_os_str))]
+        prefix: PathBuf,
+    },
+    /// Restore a file from any --data-block shards
+    Decode {
+        #[structopt(parse(from_os_str))]
+        prefix: PathBuf,
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// Check that the parity shards agree with the data shards
+    Verify {
+        #[structopt(parse(from_os_str))]
+        prefix: PathBuf,
+    },
+    /// Print the average compression, memory access and cache ratios of Section 7.3
+    /// over the encoding program and all the decoding ones
+    Report,
 }
 
 fn mean(vs: &[f64]) -> f64 {
@@ -80,53 +134,73 @@ fn sd(v: &[f64], mean: f64) -> f64 {
     sd.sqrt()
 }
 
-fn avg_throughput(prefix: &str, times: &[f64], data_size: usize) {
+fn avg_throughput(prefix: &str, times: &[f64], data_size: usize) -> f64 {
     let throughputs: Vec<_> = times.iter().map(|e| (data_size as f64) / e).collect();
     let m = mean(&throughputs);
     let sd = sd(&throughputs, m);
     println!("{}: avg = {} MB/s, sd = {}", prefix, m, sd);
+    m
 }
 
 fn ceilup(data_size: usize, unit: usize) -> usize {
-    if data_size % unit == 0 {
+    if data_size.is_multiple_of(unit) {
         data_size
     } else {
         ((data_size + unit) / unit) * unit
     }
 }
 
-fn optimize_program(
-    slp: &slp::SLP,
-    compress: bool,
-    level: OptimizeLevel,
-) -> for_benchmark::PebbleProgram {
-    let graph = if compress {
-        for_benchmark::xor_repair(&slp)
-    } else {
-        slp.to_trivial_graph()
-    };
+// the largest of --threads, or one worker per CPU
+fn nr_workers(threads: &Option<Vec<usize>>) -> usize {
+    threads
+        .as_ref()
+        .and_then(|ts| ts.iter().max().cloned())
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+        })
+}
 
-    match level {
-        OptimizeLevel::Nooptim => {
-            let (_, program) = for_benchmark::graph_analyze(&slp, &graph);
-            program
-        }
-        OptimizeLevel::Fusion => {
-            let (_, program) = for_benchmark::bench_fusion(&slp, &graph);
-            program
-        }
-        OptimizeLevel::FusionSchedule => {
-            let (_, _, _, _, program) = for_benchmark::bench_pebble(&slp, &graph);
-            program
-        }
-    }
+// the 8 bit-planes of each of `survivors`, taken from the data or the parity planes
+fn decode_inputs<'a>(
+    survivors: &[usize],
+    nr_data_block: usize,
+    data: &[&'a [u8]],
+    parity: &'a [&mut [u8]],
+) -> Vec<&'a [u8]> {
+    survivors
+        .iter()
+        .flat_map(|&s| {
+            (s * 8..(s + 1) * 8).map(move |i| {
+                if i < nr_data_block * 8 {
+                    data[i]
+                } else {
+                    &*parity[i - nr_data_block * 8]
+                }
+            })
+        })
+        .collect()
 }
 
-fn main() {
+fn main() -> Result<(), XorSlpError> {
     let opt = Opt::from_args();
 
     // dbg!(&opt);
-    println!("Block size = {}", xorslp_ec::BLOCK_SIZE_PER_ITER);
+    let block_size = opt.block_size.unwrap_or(xorslp_ec::BLOCK_SIZE_PER_ITER);
+    run::check_block_size(block_size).map_err(XorSlpError::InvalidParameter)?;
+
+    // nothing but the statistics goes to stdout in the JSON and CSV formats
+    let text = opt.format == OutputFormat::Text;
+    if text {
+        println!("Block size = {}", block_size);
+    }
+
+    let kernel = opt.kernel.unwrap_or_else(Kernel::detect);
+    if !kernel.is_available() {
+        return Err(run::RunError::UnavailableKernel(kernel).into());
+    }
+    if text {
+        println!("Kernel = {:?}", kernel);
+    }
 
     let loop_iter = opt.loop_iter.unwrap_or(1000);
     let nr_data_block = opt.data_block.unwrap_or(10);
@@ -153,124 +227,206 @@ fn main() {
 
     // enc_slp.pprint();
 
-    if opt.stat_sec75 {
-        println!("Statistics for Encoding");
-        xorslp_ec::comparison::sec75_stat(&enc_slp);
+    let options = Options {
+        compress: !opt.no_compress,
+        level: opt.optimize_level,
+        block_size,
+        scheduler: opt.scheduler.unwrap_or_default(),
+        nr_threads: 1,
+    };
+    // the statistics of the paper are those of the bottom-up MRU schedules
+    let stat_options = Options {
+        scheduler: opt.scheduler.unwrap_or(Scheduler::BottomUpMru),
+        ..options
+    };
+
+    match &opt.command {
+        Some(Command::Encode { input, prefix }) => {
+            let encoder = encoder::Encoder::new(rs_parameter, options)?;
+            let manifest = files::encode_file(input, prefix, &encoder, 64 * 8 * block_size)?;
+            println!(
+                "Encoded {} bytes into {} shards of {} bytes",
+                manifest.object_len,
+                manifest.nr_shards(),
+                manifest.shard_file_len()
+            );
+            return Ok(());
+        }
+        Some(Command::Decode { prefix, output }) => {
+            let erased = files::decode_file(prefix, output, options)?;
+            println!(
+                "Decoded {:?} (shards with erased stripes: {:?})",
+                output, erased
+            );
+            return Ok(());
+        }
+        Some(Command::Verify { prefix }) => {
+            files::verify_file(prefix, options)?;
+            println!("All the parity shards are consistent");
+            return Ok(());
+        }
+        Some(Command::Report) => {
+            let stats = report::collect(rs_parameter, &stat_options, nr_workers(&opt.threads))?;
+            let stats: Vec<_> = stats.into_iter().map(|(_, stat)| stat).collect();
+            println!("{}", report::summarize(&stats));
+            return Ok(());
+        }
+        None => (),
+    }
 
-        println!("Statistics for Decoding");
-        let mut inv = enc;
-        inv.drop_rows([2, 4, 5, 6].to_vec());
-        let inv = inv.inverse().unwrap();
+    if let Some(path) = &opt.bundle {
+        let nr_workers = nr_workers(&opt.threads);
+        println!("Writing a bundle to {:?} with {} workers", path, nr_workers);
+        let now = Instant::now();
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        bundle::write_bundle(&mut writer, rs_parameter, &options, nr_workers)?;
+        println!(
+            "{} programs in {} s",
+            bundle::Bundle::open(path)?.nr_entries(),
+            now.elapsed().as_secs_f64()
+        );
+        return Ok(());
+    }
+
+    let decoding_slp = |remove: &[usize]| -> Result<slp::SLP, XorSlpError> {
+        let inv = decoder::decode_matrix(&enc, remove)?;
         let bitmatrix_inv = rsv_bitmatrix::matrix_to_bitmatrix(&inv);
-        let inv_slp = slp::SLP::build_from_bitmatrix_not_depending_variables(&bitmatrix_inv);
-        xorslp_ec::comparison::sec75_stat(&inv_slp);
+        Ok(slp::SLP::build_from_bitmatrix_not_depending_variables(
+            &bitmatrix_inv,
+        ))
+    };
+    let mut writer = StatWriter::new(std::io::stdout(), opt.format);
 
-        return;
+    if opt.stat_sec75 {
+        writer.heading("Statistics for Encoding")?;
+        writer.write(
+            &[],
+            comparison::sec75_stat(&enc_slp, &stat_options)?.as_ref(),
+        )?;
+
+        writer.heading("Statistics for Decoding")?;
+        let remove = [2, 4, 5, 6];
+        let inv_slp = decoding_slp(&remove)?;
+        writer.write(
+            &remove,
+            comparison::sec75_stat(&inv_slp, &stat_options)?.as_ref(),
+        )?;
+
+        writer.finish()?;
+        return Ok(());
     }
 
     if opt.stat_enc {
-        println!("Statistics for Encoding");
-
-        xorslp_ec::comparison::all_stat(&enc_slp, false); // without compression
-        xorslp_ec::comparison::all_stat(&enc_slp, true); // with compression
+        writer.heading("Statistics for Encoding")?;
+
+        // without and with compression
+        for compress in [false, true] {
+            writer.write(
+                &[],
+                comparison::all_stat(&enc_slp, compress, &stat_options)?.as_ref(),
+            )?;
+        }
 
-        return;
+        writer.finish()?;
+        return Ok(());
     }
 
     if let Some(remove) = opt.stat_dec {
-        if nr_parity_block != remove.len() {
-            println!(
-                "[Error] Please Pass {} blocks (now passed {} blocks)",
+        if remove.is_empty() || nr_parity_block < remove.len() {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "Please Pass 1 to {} blocks (now passed {} blocks)",
                 nr_parity_block,
                 remove.len()
-            );
-            return;
+            )));
         }
 
-        println!("Statistics for Decoding: {:?}", remove);
-        let mut inv = enc;
-        inv.drop_rows(remove);
-        let inv = inv.inverse().unwrap();
-        let bitmatrix_inv = rsv_bitmatrix::matrix_to_bitmatrix(&inv);
-        let inv_slp = slp::SLP::build_from_bitmatrix_not_depending_variables(&bitmatrix_inv);
+        writer.heading(&format!("Statistics for Decoding: {:?}", remove))?;
+        let inv_slp = decoding_slp(&remove)?;
+
+        for compress in [false, true] {
+            writer.write(
+                &remove,
+                comparison::all_stat(&inv_slp, compress, &stat_options)?.as_ref(),
+            )?;
+        }
 
-        xorslp_ec::comparison::all_stat(&inv_slp, false); // without compression
-        xorslp_ec::comparison::all_stat(&inv_slp, true); // with compression
-        return;
+        writer.finish()?;
+        return Ok(());
     }
 
     if opt.compare_compress {
-        println!("Dump All Statistics about Compression for Encoding and Decoding Programs");
-
-        println!("Enc: ");
-        xorslp_ec::comparison::compress_stat(&enc_slp);
+        writer
+            .heading("Dump All Statistics about Compression for Encoding and Decoding Programs")?;
 
-        for it in (0..(nr_data_block + nr_parity_block)).combinations(nr_parity_block) {
-            let remove: Vec<usize> = it.to_vec();
-            let mut tmp = enc.clone();
-            tmp.drop_rows(remove.clone());
-            let inv = tmp.inverse().unwrap();
-            let bitmatrix_inv = rsv_bitmatrix::matrix_to_bitmatrix(&inv);
-            let inv_slp = slp::SLP::build_from_bitmatrix_not_depending_variables(&bitmatrix_inv);
+        writer.write(&[], comparison::compress_stat(&enc_slp)?.as_ref())?;
 
-            println!("Dec {:?}:", remove);
-            xorslp_ec::comparison::compress_stat(&inv_slp);
+        for remove in (0..(nr_data_block + nr_parity_block)).combinations(nr_parity_block) {
+            let inv_slp = decoding_slp(&remove)?;
+            writer.write(&remove, comparison::compress_stat(&inv_slp)?.as_ref())?;
         }
-        return;
+
+        writer.finish()?;
+        return Ok(());
     }
 
     if opt.all_stat {
-        println!("Dump All Statistics for Encoding and Decoding Programs");
-
-        println!("Enc: ");
-        xorslp_ec::comparison::all_stat(&enc_slp, false); // without compression
-        xorslp_ec::comparison::all_stat(&enc_slp, true); // with compression
-
-        for it in (0..(nr_data_block + nr_parity_block)).combinations(nr_parity_block) {
-            let remove: Vec<usize> = it.to_vec();
-            let mut tmp = enc.clone();
-            tmp.drop_rows(remove.clone());
-            let inv = tmp.inverse().unwrap();
-            let bitmatrix_inv = rsv_bitmatrix::matrix_to_bitmatrix(&inv);
-            let inv_slp = slp::SLP::build_from_bitmatrix_not_depending_variables(&bitmatrix_inv);
-
-            println!("Dec {:?}:", remove);
-            xorslp_ec::comparison::all_stat(&inv_slp, false);
-            xorslp_ec::comparison::all_stat(&inv_slp, true);
+        writer.heading("Dump All Statistics for Encoding and Decoding Programs")?;
+
+        for compress in [false, true] {
+            writer.write(
+                &[],
+                comparison::all_stat(&enc_slp, compress, &stat_options)?.as_ref(),
+            )?;
         }
-        return;
+
+        for remove in (0..(nr_data_block + nr_parity_block)).combinations(nr_parity_block) {
+            let inv_slp = decoding_slp(&remove)?;
+            for compress in [false, true] {
+                writer.write(
+                    &remove,
+                    comparison::all_stat(&inv_slp, compress, &stat_options)?.as_ref(),
+                )?;
+            }
+        }
+
+        writer.finish()?;
+        return Ok(());
     }
 
-    let remove = opt.enc_dec.unwrap();
-    let remove = if !remove.is_empty() {
+    let remove = opt.enc_dec.unwrap_or_default();
+    let mut remove = if !remove.is_empty() {
         remove
     } else if nr_parity_block <= 4 {
         let mut tmp = vec![2, 4, 5, 6];
         tmp.truncate(nr_parity_block);
         tmp
     } else {
-        unreachable!("Please pass blocks to be erased")
+        return Err(XorSlpError::InvalidParameter(
+            "Please pass blocks to be erased".to_owned(),
+        ));
     };
 
-    println!("Benchmarking of Encoding & Decoding (with {:?})", remove);
+    remove.sort_unstable();
+    remove.dedup();
 
-    let mut inv = enc;
-    inv.drop_rows(remove.clone());
-    let inv = inv.inverse().unwrap();
+    println!("Benchmarking of Encoding & Decoding (with {:?})", remove);
 
-    let bitmatrix_inv = rsv_bitmatrix::matrix_to_bitmatrix(&inv);
+    // the surviving blocks are decoded into the removed ones in ascending order
+    let survivors = decoder::survivors(&enc, &remove)?;
+    let bitmatrix_inv = decoder::repair_bitmatrix(&enc, &remove, &remove)?;
     let inv_slp = slp::SLP::build_from_bitmatrix_not_depending_variables(&bitmatrix_inv);
+    let dec_parameter = Parameter {
+        nr_data_block,
+        nr_parity_block: remove.len(),
+    };
 
-    let compress = !opt.no_compress; // default compress
-
-    let level = opt.optimize_level;
+    println!("Scheduler = {:?}", options.scheduler);
 
     let enc_shrinked = for_benchmark::shrink(&enc_slp);
-    let enc_program = optimize_program(&enc_shrinked, compress, level);
+    let enc_program = pipeline::optimize_program(&enc_shrinked, &options)?;
 
     let dec_shrinked = for_benchmark::shrink(&inv_slp);
-    let dec_shrinked = dec_shrinked;
-    let dec_program = optimize_program(&dec_shrinked, compress, level);
+    let dec_program = pipeline::optimize_program(&dec_shrinked, &options)?;
 
     {
         let enc_program: Vec<(Pebble, &[Pebble])> = enc_program
@@ -292,7 +448,7 @@ fn main() {
         let data_size = if cfg!(feature = "4096_align") {
             data_size
         } else {
-            data_size + xorslp_ec::BLOCK_SIZE_PER_ITER * (nr_data_block * 8)
+            data_size + block_size * (nr_data_block * 8)
         };
 
         // println!("data size = {}", data_size);
@@ -311,27 +467,21 @@ fn main() {
         let input = fixed_array.split(nr_data_block * 8);
         let width = input[0].len();
 
-        let to_store = run::PageAlignedArray::new(width * nr_parity_block * 8).unwrap();
-        let output = to_store.split(nr_parity_block * 8);
+        let mut to_store = run::PageAlignedArray::new(width * nr_parity_block * 8).unwrap();
 
-        let required_pebbles = std::cmp::max(
-            run::required_pebbles(&enc_program),
-            run::required_pebbles(&dec_program),
+        let tmp_pebbles = std::cmp::max(
+            run::required_pebbles(&enc_program) - nr_parity_block * 8,
+            run::required_pebbles(&dec_program) - remove.len() * 8,
         );
-        let tmp_pebbles = required_pebbles - nr_parity_block * 8;
 
-        let for_tmp =
-            run::PageAlignedArray::new(xorslp_ec::BLOCK_SIZE_PER_ITER * tmp_pebbles).unwrap();
-        let tmp = for_tmp.split(tmp_pebbles);
+        let mut for_tmp = run::PageAlignedArray::new(block_size * tmp_pebbles).unwrap();
 
-        let for_decode = run::PageAlignedArray::new(width * nr_parity_block * 8).unwrap();
-        let decode = for_decode.split(nr_parity_block * 8);
-        let decoded = for_decode.split(nr_parity_block);
+        let mut for_decode = run::PageAlignedArray::new(width * remove.len() * 8).unwrap();
 
         let (enc_program, dec_program) = if !opt.cache_estimate {
             (
                 run::compile(rs_parameter, &enc_program),
-                run::compile(rs_parameter, &dec_program),
+                run::compile(dec_parameter, &dec_program),
             )
         } else {
             (
@@ -341,12 +491,31 @@ fn main() {
         };
 
         for _ in 0..loop_iter {
+            let mut output = to_store.split_mut(nr_parity_block * 8);
+            let mut tmp = for_tmp.split_mut(tmp_pebbles);
+
             let now = Instant::now();
-            run::run_program(
-                &run::combine_constant_target_tmp(&input, &output, &tmp),
-                width / xorslp_ec::BLOCK_SIZE_PER_ITER,
-                &enc_program,
-            );
+            if !opt.cache_estimate {
+                run::run_slices(
+                    &enc_program,
+                    block_size,
+                    kernel,
+                    &input,
+                    &mut output,
+                    &mut tmp,
+                )?;
+            } else {
+                // the estimated program deliberately writes to the input buffers
+                unsafe {
+                    run::run_program(
+                        &run::combine_constant_target_tmp(&input, &mut output, &mut tmp),
+                        width / block_size,
+                        block_size,
+                        kernel,
+                        &enc_program,
+                    )
+                };
+            }
             enc_durations.push(now.elapsed().as_micros() as f64);
 
             let original = fixed_array.split(nr_data_block);
@@ -357,27 +526,112 @@ fn main() {
                 }
             }
 
-            // remove and add parities
-            let mut decode_input = xorslp_ec::drop8(input.clone(), &remove);
-            decode_input.append(&mut output.clone());
+            let decode_input = decode_inputs(&survivors, nr_data_block, &input, &output);
+            let mut decode = for_decode.split_mut(remove.len() * 8);
 
             let now = Instant::now();
-            run::run_program(
-                &run::combine_constant_target_tmp(&decode_input, &decode, &tmp),
-                width / xorslp_ec::BLOCK_SIZE_PER_ITER,
-                &dec_program,
-            );
+            if !opt.cache_estimate {
+                run::run_slices(
+                    &dec_program,
+                    block_size,
+                    kernel,
+                    &decode_input,
+                    &mut decode,
+                    &mut tmp,
+                )?;
+            } else {
+                unsafe {
+                    run::run_program(
+                        &run::combine_constant_target_tmp(&decode_input, &mut decode, &mut tmp),
+                        width / block_size,
+                        block_size,
+                        kernel,
+                        &dec_program,
+                    )
+                };
+            }
             dec_durations.push(now.elapsed().as_micros() as f64);
 
             if !opt.cache_estimate {
-                for i in 0..nr_parity_block {
-                    assert!(inputs[remove[i]] == original[remove[i]]);
-                    assert!(decoded[i] == original[remove[i]]);
+                let parity = to_store.split(nr_parity_block);
+                let decoded = for_decode.split(remove.len());
+                for (i, &r) in remove.iter().enumerate() {
+                    let expected = if r < nr_data_block {
+                        original[r]
+                    } else {
+                        parity[r - nr_data_block]
+                    };
+                    assert!(decoded[i] == expected);
                 }
             }
         }
 
-        avg_throughput("Encode", &enc_durations, data_size);
-        avg_throughput("Decode", &dec_durations, data_size);
+        let enc_single = avg_throughput("Encode", &enc_durations, data_size);
+        let dec_single = avg_throughput("Decode", &dec_durations, data_size);
+
+        // scaling of the multi-threaded execution
+        let threads = if opt.cache_estimate {
+            Vec::new()
+        } else {
+            opt.threads.clone().unwrap_or_default()
+        };
+        for nr_threads in threads {
+            let mut enc_durations = Vec::new();
+            let mut dec_durations = Vec::new();
+
+            for _ in 0..loop_iter {
+                let mut output = to_store.split_mut(nr_parity_block * 8);
+
+                let now = Instant::now();
+                run::run_slices_parallel(
+                    &enc_program,
+                    block_size,
+                    kernel,
+                    tmp_pebbles,
+                    nr_threads,
+                    &input,
+                    &mut output,
+                )?;
+                enc_durations.push(now.elapsed().as_micros() as f64);
+
+                let decode_input = decode_inputs(&survivors, nr_data_block, &input, &output);
+                let mut decode = for_decode.split_mut(remove.len() * 8);
+
+                let now = Instant::now();
+                run::run_slices_parallel(
+                    &dec_program,
+                    block_size,
+                    kernel,
+                    tmp_pebbles,
+                    nr_threads,
+                    &decode_input,
+                    &mut decode,
+                )?;
+                dec_durations.push(now.elapsed().as_micros() as f64);
+            }
+
+            let original = fixed_array.split(nr_data_block);
+            let parity = to_store.split(nr_parity_block);
+            let decoded = for_decode.split(remove.len());
+            for (i, &r) in remove.iter().enumerate() {
+                let expected = if r < nr_data_block {
+                    original[r]
+                } else {
+                    parity[r - nr_data_block]
+                };
+                assert!(decoded[i] == expected);
+            }
+
+            println!("Threads = {}", nr_threads);
+            let enc = avg_throughput("Encode", &enc_durations, data_size);
+            let dec = avg_throughput("Decode", &dec_durations, data_size);
+            println!(
+                "Speedup: encode = {:.2}, decode = {:.2}",
+                enc / enc_single,
+                dec / dec_single
+            );
+        }
     }
+
+    Ok(())
 }
@@ -279,7 +279,7 @@ mod test {
         m[2][2] = l;
         m[2][3] = l;
 
-        return m;
+        m
     }
 
     /*
@@ -305,7 +305,7 @@ mod test {
         m[2][1] = o;
         m[2][2] = l;
 
-        return m;
+        m
     }
 
     /*
@@ -331,7 +331,7 @@ mod test {
         m[2][1] = o;
         m[2][2] = o;
 
-        return m;
+        m
     }
 
     /*
@@ -357,7 +357,7 @@ mod test {
         m[2][1] = o;
         m[2][2] = o;
 
-        return m;
+        m
     }
 
     /*
@@ -383,7 +383,7 @@ mod test {
         m[2][1] = l;
         m[2][2] = o;
 
-        return m;
+        m
     }
 
     /*
@@ -409,7 +409,7 @@ mod test {
         m[2][1] = l;
         m[2][2] = o;
 
-        return m;
+        m
     }
 
     #[test]
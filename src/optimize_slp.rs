@@ -23,7 +23,7 @@ pub fn step1(slp: &SLP) -> SLP {
 
     for i in 0..slp.num_of_variables() {
         let def = &slp[i];
-        if popcount(&def) > 1 {
+        if popcount(def) > 1 {
             let current = bitmatrix.len();
             v.push((current, i));
             bitmatrix.push(def.clone());
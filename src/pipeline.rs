@@ -0,0 +1,252 @@
+use crate::bitmatrix::{popcount, BitMatrix};
+use crate::error::XorSlpError;
+use crate::for_benchmark::{self, PebbleProgram};
+use crate::reorder::Pebble;
+use crate::run::{self, Program};
+use crate::slp::SLP;
+use crate::{BLOCK_SIZE_PER_ITER, L1_CACHE_SIZE};
+use clap::arg_enum;
+
+arg_enum! {
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum OptimizeLevel {
+        Nooptim,
+        Fusion,
+        FusionSchedule,
+    }
+}
+
+arg_enum! {
+    // Dfs = reorder::deal_multislp, BottomUp = reorder2::deal_multislp2
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum Scheduler {
+        DfsLru,
+        DfsMru,
+        BottomUpLru,
+        BottomUpMru,
+        Auto, // the one with the fewest page transfers
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        if cfg!(feature = "bottomup_sched") {
+            Scheduler::BottomUpMru
+        } else {
+            Scheduler::DfsMru
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    pub compress: bool, // use XorRePair
+    pub level: OptimizeLevel,
+    pub block_size: usize,    // bytes processed per iteration; a multiple of 64
+    pub scheduler: Scheduler, // used by OptimizeLevel::FusionSchedule
+    pub nr_threads: usize,    // worker threads running a compiled program
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            compress: true,
+            level: OptimizeLevel::FusionSchedule,
+            block_size: BLOCK_SIZE_PER_ITER,
+            scheduler: Scheduler::default(),
+            nr_threads: 1,
+        }
+    }
+}
+
+impl Options {
+    // the number of blocks fitting in the L1 cache
+    pub fn nr_pebbles(&self) -> usize {
+        std::cmp::max(L1_CACHE_SIZE / self.block_size, 1)
+    }
+}
+
+/// `slp` is assumed to be shrinked (see `for_benchmark::shrink`)
+pub fn optimize_program(slp: &SLP, options: &Options) -> Result<PebbleProgram, XorSlpError> {
+    run::check_block_size(options.block_size).map_err(XorSlpError::InvalidParameter)?;
+
+    let graph = if options.compress {
+        for_benchmark::xor_repair(slp)
+    } else {
+        slp.to_trivial_graph()?
+    };
+
+    let program = match options.level {
+        OptimizeLevel::Nooptim => for_benchmark::graph_analyze(slp, &graph)?.1,
+        OptimizeLevel::Fusion => for_benchmark::bench_fusion(slp, &graph)?.1,
+        OptimizeLevel::FusionSchedule => {
+            for_benchmark::bench_pebble(slp, &graph, options.nr_pebbles(), options.scheduler)?.1
+        }
+    };
+
+    Ok(program)
+}
+
+// The targets of the rows of `bitmatrix` and the optimized program computing them
+fn optimize_bitmatrix(
+    bitmatrix: &BitMatrix,
+    options: &Options,
+) -> Result<(Vec<Option<Pebble>>, PebbleProgram), XorSlpError> {
+    let mut goals: Vec<Vec<bool>> = Vec::new();
+    let mut targets: Vec<Option<Pebble>> = Vec::new();
+
+    for row in &bitmatrix.inner {
+        let target = match popcount(row) {
+            0 => None,
+            1 => Some(Pebble::Const(row.iter().position(|b| *b).unwrap())),
+            _ => {
+                let idx = goals.iter().position(|g| g == row).unwrap_or_else(|| {
+                    goals.push(row.clone());
+                    goals.len() - 1
+                });
+                Some(Pebble::Var(idx))
+            }
+        };
+        targets.push(target);
+    }
+
+    let program = if goals.is_empty() {
+        PebbleProgram::new()
+    } else {
+        let slp =
+            SLP::build_from_bitmatrix_not_depending_variables(&BitMatrix::from_nested_vecs(goals));
+        optimize_program(&slp, options)?
+    };
+
+    Ok((targets, program))
+}
+
+/// bitmatrix -> SLP -> optimized program -> compiled program.
+///
+/// The `i`-th row of `bitmatrix` becomes the `i`-th output of the program.
+/// Rows with at most one `1` need no XOR and are compiled into copies (or zero-fills),
+/// and duplicated rows are computed only once.
+pub fn compile_bitmatrix(bitmatrix: &BitMatrix, options: &Options) -> Result<Program, XorSlpError> {
+    compile_bitmatrices(std::slice::from_ref(bitmatrix), options)
+}
+
+/// `compile_bitmatrix` for a program XORing the rows into its outputs (see `Program::compile_accumulate`).
+pub fn compile_bitmatrix_accumulate(
+    bitmatrix: &BitMatrix,
+    options: &Options,
+) -> Result<Program, XorSlpError> {
+    let nr_inputs = bitmatrix.inner.first().map_or(0, |row| row.len());
+    let (targets, program) = optimize_bitmatrix(bitmatrix, options)?;
+
+    Ok(
+        Program::compile_accumulate(nr_inputs, &targets, &program, options.block_size)?
+            .with_threads(options.nr_threads),
+    )
+}
+
+/// `compile_bitmatrix` for the rows of `bitmatrices` stacked in order,
+/// where each bitmatrix is optimized as a separate SLP.
+/// All the bitmatrices must have the same width.
+pub fn compile_bitmatrices(
+    bitmatrices: &[BitMatrix],
+    options: &Options,
+) -> Result<Program, XorSlpError> {
+    let nr_inputs = bitmatrices
+        .iter()
+        .flat_map(|m| m.inner.first())
+        .next()
+        .map_or(0, |row| row.len());
+    let (targets, program) = optimize_bitmatrices(bitmatrices, options)?;
+
+    Ok(
+        Program::compile(nr_inputs, &targets, &program, options.block_size)?
+            .with_threads(options.nr_threads),
+    )
+}
+
+/// The targets of the stacked rows of `bitmatrices` and the optimized program computing them,
+/// which `Program::compile` takes (see `compile_bitmatrices`).
+pub fn optimize_bitmatrices(
+    bitmatrices: &[BitMatrix],
+    options: &Options,
+) -> Result<(Vec<Option<Pebble>>, PebbleProgram), XorSlpError> {
+    let nr_inputs = bitmatrices
+        .iter()
+        .flat_map(|m| m.inner.first())
+        .next()
+        .map_or(0, |row| row.len());
+
+    let mut targets: Vec<Option<Pebble>> = Vec::new();
+    let mut program = PebbleProgram::new();
+
+    for bitmatrix in bitmatrices {
+        if bitmatrix.inner.iter().any(|row| row.len() != nr_inputs) {
+            return Err(XorSlpError::InvalidParameter(
+                "bitmatrices of different widths".to_owned(),
+            ));
+        }
+
+        let (part_targets, part_program) = optimize_bitmatrix(bitmatrix, options)?;
+
+        // rename the variables apart from the previous parts
+        let offset = program
+            .iter()
+            .flat_map(|(t, vs)| std::iter::once(t).chain(vs.iter()))
+            .filter_map(|p| match p {
+                Pebble::Var(v) => Some(v + 1),
+                Pebble::Const(_) => None,
+            })
+            .max()
+            .unwrap_or(0);
+        let shift = |p: &Pebble| match p {
+            Pebble::Var(v) => Pebble::Var(v + offset),
+            Pebble::Const(c) => Pebble::Const(*c),
+        };
+
+        targets.extend(part_targets.iter().map(|t| t.as_ref().map(shift)));
+        program.extend(
+            part_program
+                .iter()
+                .map(|(t, vs)| (shift(t), vs.iter().map(shift).collect())),
+        );
+    }
+
+    Ok((targets, program))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rsv_bitmatrix, stat, vandermonde};
+
+    #[test]
+    fn scheduler_test() {
+        let mut m = vandermonde::isa_rsv(4, 2);
+        m.drop_rows((0..4).collect());
+        let bitmatrix = rsv_bitmatrix::matrix_to_bitmatrix(&m);
+        let slp = for_benchmark::shrink(&SLP::build_from_bitmatrix_not_depending_variables(
+            &bitmatrix,
+        ));
+
+        let transfers = |scheduler| {
+            let options = Options {
+                scheduler,
+                ..Options::default()
+            };
+            let program = optimize_program(&slp, &options).unwrap();
+            stat::analyze(&program).nr_page_transfer
+        };
+
+        let auto = transfers(Scheduler::Auto);
+        let others: Vec<usize> = [
+            Scheduler::DfsLru,
+            Scheduler::DfsMru,
+            Scheduler::BottomUpLru,
+            Scheduler::BottomUpMru,
+        ]
+        .iter()
+        .map(|&s| transfers(s))
+        .collect();
+        assert_eq!(Some(&auto), others.iter().min());
+    }
+}
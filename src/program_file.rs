@@ -0,0 +1,460 @@
+use crate::bitmatrix::BitMatrix;
+use crate::decoder;
+use crate::error::XorSlpError;
+use crate::fin_field::GF_2_8;
+use crate::for_benchmark::PebbleProgram;
+use crate::matrix::Matrix;
+use crate::pipeline::{self, Options};
+use crate::reorder::Pebble;
+use crate::run::{self, Program};
+use crate::validation::pebble_computation_to_valuation;
+use crate::*;
+use std::collections::BTreeSet;
+use std::io::{Read, Write};
+
+/*
+ * An optimized program stored on disk, so that it can be shipped precomputed.
+ * All the integers are little endian:
+ *
+ *   magic "XSLP", version: u32,
+ *   nr_data_block: u32, nr_parity_block: u32, generator hash: u64,
+ *   #erased: u32, erased: u32 * #erased, #wanted: u32, wanted: u32 * #wanted,
+ *   block_size: u32,
+ *   #targets: u32, targets: u32 * #targets,
+ *   #steps: u32, and for each step: target: u32, #children: u32, children: u32 * #children
+ *
+ * A pebble is `(index << 1) | is_var`, and a target `NO_TARGET` is a zero output.
+ * Encoding programs are those with `erased = wanted = parity blocks`.
+ */
+const MAGIC: &[u8; 4] = b"XSLP";
+pub const FORMAT_VERSION: u32 = 1;
+const NO_TARGET: u32 = u32::MAX;
+
+/// FNV-1a of the shape and the entries of `generator`,
+/// which tells whether a program was made for the same generator matrix.
+pub fn generator_hash(generator: &Matrix<GF_2_8>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |b: u8| {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    };
+
+    for b in (generator.height() as u32)
+        .to_le_bytes()
+        .iter()
+        .chain((generator.width() as u32).to_le_bytes().iter())
+    {
+        feed(*b);
+    }
+    for i in 0..generator.height() {
+        for j in 0..generator.width() {
+            feed(generator.get(i, j).unwrap().into());
+        }
+    }
+    hash
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramFile {
+    parameter: Parameter,
+    erased: Vec<usize>,
+    wanted: Vec<usize>,
+    block_size: usize,
+    targets: Vec<Option<Pebble>>,
+    program: PebbleProgram,
+}
+
+impl ProgramFile {
+    /// Optimize the program of `Decoder::subset_program(erased, wanted)`.
+    pub fn new(
+        parameter: Parameter,
+        erased: Vec<usize>,
+        wanted: Vec<usize>,
+        options: &Options,
+    ) -> Result<Self, XorSlpError> {
+        parameter.check()?;
+        let generator = vandermonde::isa_rsv(parameter.nr_data_block, parameter.nr_parity_block);
+        let bitmatrix = decoder::repair_bitmatrix(&generator, &erased, &wanted)?;
+        let (targets, program) =
+            pipeline::optimize_bitmatrices(std::slice::from_ref(&bitmatrix), options)?;
+
+        Ok(ProgramFile {
+            parameter,
+            erased,
+            wanted,
+            block_size: options.block_size,
+            targets,
+            program,
+        })
+    }
+
+    /// Optimize the encoding program, which computes the parity blocks from the data blocks.
+    pub fn encoding(parameter: Parameter, options: &Options) -> Result<Self, XorSlpError> {
+        let parity: Vec<usize> = (parameter.nr_data_block
+            ..parameter.nr_data_block + parameter.nr_parity_block)
+            .collect();
+        Self::new(parameter, parity.clone(), parity, options)
+    }
+
+    pub fn parameter(&self) -> Parameter {
+        self.parameter
+    }
+
+    pub fn erased(&self) -> &[usize] {
+        &self.erased
+    }
+
+    pub fn wanted(&self) -> &[usize] {
+        &self.wanted
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// The program taking the `8 * nr_data_block` bit-planes of `decoder::survivors(erased)`
+    /// and computing those of `wanted`.
+    pub fn compile(&self) -> Result<Program, XorSlpError> {
+        Program::compile(
+            8 * self.parameter.nr_data_block,
+            &self.targets,
+            &self.program,
+            self.block_size,
+        )
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), XorSlpError> {
+        let generator =
+            vandermonde::isa_rsv(self.parameter.nr_data_block, self.parameter.nr_parity_block);
+
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend_from_slice(MAGIC);
+        put(&mut buf, FORMAT_VERSION);
+        put(&mut buf, self.parameter.nr_data_block as u32);
+        put(&mut buf, self.parameter.nr_parity_block as u32);
+        buf.extend_from_slice(&generator_hash(&generator).to_le_bytes());
+        for blocks in [&self.erased, &self.wanted] {
+            put(&mut buf, blocks.len() as u32);
+            for b in blocks.iter() {
+                put(&mut buf, *b as u32);
+            }
+        }
+        put(&mut buf, self.block_size as u32);
+
+        put(&mut buf, self.targets.len() as u32);
+        for t in &self.targets {
+            put(&mut buf, t.as_ref().map_or(NO_TARGET, encode_pebble));
+        }
+        put(&mut buf, self.program.len() as u32);
+        for (t, children) in &self.program {
+            put(&mut buf, encode_pebble(t));
+            put(&mut buf, children.len() as u32);
+            for c in children {
+                put(&mut buf, encode_pebble(c));
+            }
+        }
+
+        writer.write_all(&buf)?;
+        Ok(())
+    }
+
+    /// Load a program written by `write`.
+    ///
+    /// Besides the header, the program itself is validated:
+    /// its targets have to be the rows of `decoder::repair_bitmatrix` for the erasure set,
+    /// which is checked with `validation::pebble_computation_to_valuation`.
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, XorSlpError> {
+        let mut magic = [0u8; 4];
+        read_exact(reader, &mut magic)?;
+        if &magic != MAGIC {
+            return Err(XorSlpError::MalformedInput("not a program file".to_owned()));
+        }
+        let version = get(reader)?;
+        if version != FORMAT_VERSION {
+            return Err(XorSlpError::MalformedInput(format!(
+                "format version {} is not supported",
+                version
+            )));
+        }
+
+        let parameter = Parameter {
+            nr_data_block: get(reader)? as usize,
+            nr_parity_block: get(reader)? as usize,
+        };
+        parameter.check()?;
+        let generator = vandermonde::isa_rsv(parameter.nr_data_block, parameter.nr_parity_block);
+        let mut hash = [0u8; 8];
+        read_exact(reader, &mut hash)?;
+        if u64::from_le_bytes(hash) != generator_hash(&generator) {
+            return Err(XorSlpError::MalformedInput(
+                "the program is for another generator matrix".to_owned(),
+            ));
+        }
+
+        let erased = get_vec(reader)?.into_iter().map(|b| b as usize).collect();
+        let wanted = get_vec(reader)?.into_iter().map(|b| b as usize).collect();
+        let block_size = get(reader)? as usize;
+        run::check_block_size(block_size).map_err(XorSlpError::MalformedInput)?;
+
+        let targets = get_vec(reader)?
+            .into_iter()
+            .map(|t| {
+                if t == NO_TARGET {
+                    None
+                } else {
+                    Some(decode_pebble(t))
+                }
+            })
+            .collect();
+        let mut program = PebbleProgram::new();
+        for _ in 0..get(reader)? {
+            let t = decode_pebble(get(reader)?);
+            let children = get_vec(reader)?.into_iter().map(decode_pebble).collect();
+            program.push((t, children));
+        }
+
+        let file = ProgramFile {
+            parameter,
+            erased,
+            wanted,
+            block_size,
+            targets,
+            program,
+        };
+        let bitmatrix = decoder::repair_bitmatrix(&generator, &file.erased, &file.wanted)?;
+        file.validate(&bitmatrix)?;
+        Ok(file)
+    }
+
+    // Whether the targets are the rows of `bitmatrix`
+    fn validate(&self, bitmatrix: &BitMatrix) -> Result<(), XorSlpError> {
+        let fail = |msg: String| Err(XorSlpError::VerificationFailed(msg));
+
+        if self.targets.len() != bitmatrix.height() {
+            return fail(format!(
+                "{} targets for {} rows",
+                self.targets.len(),
+                bitmatrix.height()
+            ));
+        }
+
+        // pebble_computation_to_valuation assumes that variables are defined before use
+        let mut defined = BTreeSet::new();
+        for (t, children) in &self.program {
+            for c in children {
+                let ok = match c {
+                    Pebble::Const(c) => *c < bitmatrix.width(),
+                    Pebble::Var(_) => defined.contains(c),
+                };
+                if !ok {
+                    return fail(format!("{} is not available at {}", c, t));
+                }
+            }
+            if !t.is_var() {
+                return fail(format!("{} is not a variable", t));
+            }
+            defined.insert(t.clone());
+        }
+
+        let valuation = pebble_computation_to_valuation(&self.program);
+        for (i, (target, row)) in self.targets.iter().zip(&bitmatrix.inner).enumerate() {
+            let expected: BTreeSet<Term> = row
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| **b)
+                .map(|(j, _)| Term::Cst(j))
+                .collect();
+            let actual = match target {
+                None => BTreeSet::new(),
+                Some(Pebble::Const(c)) => std::iter::once(Term::Cst(*c)).collect(),
+                Some(v) => match valuation.get(&v.to_term()) {
+                    Some(val) => val.clone(),
+                    None => return fail(format!("{} is never computed", v)),
+                },
+            };
+            if actual != expected {
+                return fail(format!("output {} is wrong", i));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn encode_pebble(p: &Pebble) -> u32 {
+    match p {
+        Pebble::Const(c) => (*c as u32) << 1,
+        Pebble::Var(v) => ((*v as u32) << 1) | 1,
+    }
+}
+
+fn decode_pebble(p: u32) -> Pebble {
+    let idx = (p >> 1) as usize;
+    if p & 1 == 1 {
+        Pebble::Var(idx)
+    } else {
+        Pebble::Const(idx)
+    }
+}
+
+fn put(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+// a file ending too early is malformed rather than an I/O error
+fn read_exact<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<(), XorSlpError> {
+    reader.read_exact(buf).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::UnexpectedEof {
+            XorSlpError::MalformedInput("the program file is truncated".to_owned())
+        } else {
+            e.into()
+        }
+    })
+}
+
+fn get<R: Read>(reader: &mut R) -> Result<u32, XorSlpError> {
+    let mut bytes = [0u8; 4];
+    read_exact(reader, &mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+// a length followed by the elements;
+// they are read one by one, so that a broken length does not allocate much
+fn get_vec<R: Read>(reader: &mut R) -> Result<Vec<u32>, XorSlpError> {
+    let len = get(reader)?;
+    let mut v = Vec::new();
+    for _ in 0..len {
+        v.push(get(reader)?);
+    }
+    Ok(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::Decoder;
+    use crate::encoder::Encoder;
+
+    const PARAM: Parameter = Parameter {
+        nr_data_block: 4,
+        nr_parity_block: 2,
+    };
+
+    #[test]
+    fn round_trip_test() {
+        let options = Options::default();
+        let decoder = Decoder::new(PARAM, options).unwrap();
+        let len = 8 * 64 * 3;
+
+        let mut shards = vec![0u8; len * 6];
+        fill_by_random(&mut shards[..len * 4]);
+        let file = ProgramFile::encoding(PARAM, &options).unwrap();
+        let mut bytes = Vec::new();
+        file.write(&mut bytes).unwrap();
+        let loaded = ProgramFile::read(&mut &bytes[..]).unwrap();
+        assert_eq!(loaded, file);
+        {
+            let (data, parity) = shards.split_at_mut(len * 4);
+            let data_shards: Vec<&[u8]> = data.chunks(len).collect();
+            let mut parity_shards: Vec<&mut [u8]> = parity.chunks_mut(len).collect();
+            let program = loaded.compile().unwrap();
+            program
+                .run_on_shards(&data_shards, &mut parity_shards)
+                .unwrap();
+        }
+        let encoder = Encoder::new(PARAM, options).unwrap();
+        encoder
+            .verify(&shards.chunks(len).collect::<Vec<_>>())
+            .unwrap();
+
+        let erased = vec![1, 4];
+        let file = ProgramFile::new(PARAM, erased.clone(), vec![1], &options).unwrap();
+        let mut bytes = Vec::new();
+        file.write(&mut bytes).unwrap();
+        let program = ProgramFile::read(&mut &bytes[..])
+            .unwrap()
+            .compile()
+            .unwrap();
+
+        let survivors = decoder.survivors(&erased).unwrap();
+        let inputs: Vec<&[u8]> = survivors
+            .iter()
+            .map(|&i| &shards[len * i..len * (i + 1)])
+            .collect();
+        let mut recovered = vec![0u8; len];
+        program
+            .run_on_shards(&inputs, &mut [&mut recovered[..]])
+            .unwrap();
+        assert!(recovered == shards[len..len * 2]);
+
+        // the decoder serves the preloaded program without compiling
+        decoder.preload(&file).unwrap();
+        assert_eq!(decoder.nr_cached(), 1);
+        let mut broken = shards.clone();
+        broken[len..len * 2].fill(0);
+        let mut chunks: Vec<&mut [u8]> = broken.chunks_mut(len).collect();
+        let present = [true, false, true, true, false, true];
+        decoder
+            .reconstruct_subset(&mut chunks, &present, &[1])
+            .unwrap();
+        assert_eq!(decoder.nr_cached(), 1);
+        assert!(broken[len..len * 2] == shards[len..len * 2]);
+
+        let other = Decoder::new(
+            Parameter {
+                nr_data_block: 3,
+                nr_parity_block: 2,
+            },
+            options,
+        )
+        .unwrap();
+        assert!(other.preload(&file).is_err());
+    }
+
+    #[test]
+    fn validation_test() {
+        let file = ProgramFile::new(PARAM, vec![0, 5], vec![0, 5], &Options::default()).unwrap();
+        let mut bytes = Vec::new();
+        file.write(&mut bytes).unwrap();
+
+        let read = |bytes: &[u8]| ProgramFile::read(&mut &bytes[..]);
+        assert!(matches!(
+            read(&bytes[..bytes.len() - 1]),
+            Err(XorSlpError::MalformedInput(_))
+        ));
+
+        // the version, and the generator hash after the parameters
+        for offset in [4, 16] {
+            let mut broken = bytes.clone();
+            broken[offset] ^= 1;
+            assert!(matches!(read(&broken), Err(XorSlpError::MalformedInput(_))));
+        }
+
+        // a program computing something else
+        let mut broken = file.clone();
+        let (_, children) = broken
+            .program
+            .iter_mut()
+            .find(|(_, vs)| vs.iter().any(|c| !c.is_var()))
+            .unwrap();
+        let c = children.iter_mut().find(|c| !c.is_var()).unwrap();
+        *c = Pebble::Const((c.from_const().unwrap() + 1) % 32);
+        let mut bytes = Vec::new();
+        broken.write(&mut bytes).unwrap();
+        assert!(matches!(
+            read(&bytes),
+            Err(XorSlpError::VerificationFailed(_))
+        ));
+
+        // a variable used before defined
+        let mut broken = file;
+        broken.program.swap(0, 1);
+        broken.program[0].1.push(Pebble::Var(1 << 20));
+        let mut bytes = Vec::new();
+        broken.write(&mut bytes).unwrap();
+        assert!(matches!(
+            read(&bytes),
+            Err(XorSlpError::VerificationFailed(_))
+        ));
+    }
+}
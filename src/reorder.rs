@@ -112,6 +112,15 @@ where
     pub fn len(&self) -> usize {
         self.inner.len()
     }
+
+    // remove and return the least recently used element
+    pub fn evict(&mut self) -> Option<T> {
+        if self.inner.is_empty() {
+            None
+        } else {
+            Some(self.inner.remove(0))
+        }
+    }
 }
 
 pub type RecentlyUse = GenericRecentlyUse<Pebble>;
@@ -128,10 +137,16 @@ pub struct Alloc {
     mapping: BTreeMap<Term, Pebble>,
     fresh: usize,
     targets: Vec<Term>,
+    nr_pebbles: usize, // the number of pebbles fitting in the cache
 }
 
 impl Alloc {
-    pub fn new(num_of_constants: usize, targets: Vec<Term>, strategy: Strategy) -> Self {
+    pub fn new(
+        num_of_constants: usize,
+        targets: Vec<Term>,
+        strategy: Strategy,
+        nr_pebbles: usize,
+    ) -> Self {
         let mut mapping = BTreeMap::new();
         for i in 0..num_of_constants {
             mapping.insert(Term::Cst(i), Pebble::Const(i));
@@ -144,6 +159,7 @@ impl Alloc {
             mapping,
             fresh: 0,
             targets,
+            nr_pebbles,
         }
     }
 
@@ -183,7 +199,7 @@ impl Alloc {
 
     pub fn is_hot(&self, t: &Term) -> bool {
         let pebble = self.get(t).unwrap();
-        self.ru.is_hot(&pebble)
+        self.ru.is_in(&pebble, self.nr_pebbles)
     }
 
     pub fn index(&self, t: &Term) -> Option<usize> {
@@ -200,7 +216,7 @@ impl Alloc {
             return pebble;
         }
 
-        let pebble = if let Some(l1_pebble) = self.search_within(PEBBLE_NUM) {
+        let pebble = if let Some(l1_pebble) = self.search_within(self.nr_pebbles) {
             l1_pebble
         } else {
             self.search_within(self.ru.len()).unwrap()
@@ -219,7 +235,7 @@ pub fn multi_slp_to_pebble_computation(slp: &MultiSLP) -> Vec<(Pebble, Vec<Pebbl
     let mut v = Vec::new();
 
     for (t, seq) in slp {
-        let seq: Vec<_> = seq.iter().map(|t| Pebble::from_term(t)).collect();
+        let seq: Vec<_> = seq.iter().map(Pebble::from_term).collect();
         v.push((Pebble::from_term(t), seq));
     }
 
@@ -278,8 +294,8 @@ fn assign(
             }
         }
 
-        let pebble = alloc.assign(&target);
-        alloc.access(&target);
+        let pebble = alloc.assign(target);
+        alloc.access(target);
 
         real_computation.push((pebble, pebbles));
     }
@@ -362,10 +378,11 @@ pub fn deal_multislp(
     num_of_constants: usize,
     targets: Vec<Term>,
     strategy: Strategy,
+    nr_pebbles: usize,
 ) -> Vec<(Pebble, Vec<Pebble>)> {
     let dag = multislp_to_dag(slp);
 
-    let mut alloc = Alloc::new(num_of_constants, targets, strategy);
+    let mut alloc = Alloc::new(num_of_constants, targets, strategy, nr_pebbles);
     let mut out_degrees: BTreeMap<Term, usize> = make_outdegrees(&dag);
 
     let root_nodes: Vec<Term> = dag
@@ -402,7 +419,7 @@ pub fn term_slp_to_pebble_slp(slp: &[(Term, Vec<Term>)]) -> Vec<(Pebble, Vec<Peb
     let mut new_slp = Vec::new();
 
     for (p, body) in slp {
-        let body: Vec<Pebble> = body.iter().map(|p| Pebble::from_term(p)).collect();
+        let body: Vec<Pebble> = body.iter().map(Pebble::from_term).collect();
         new_slp.push((Pebble::from_term(p), body));
     }
 
@@ -415,10 +432,10 @@ pub fn finalize(
     mapping1: &[(usize, usize)], // shrinked -> original
     mapping2: &[(usize, usize)], // optimized -> shrinked
 ) -> Vec<(Term, Vec<Term>)> {
-    let mapping = optimize_slp::compose(&mapping2, &mapping1);
+    let mapping = optimize_slp::compose(mapping2, mapping1);
     let renaming = renaming::mapping_to_rewriting(&mapping);
 
-    let mut program = renaming::rename_multislp_by(&renaming, &program);
+    let mut program = renaming::rename_multislp_by(&renaming, program);
 
     let not_shrinked: Vec<usize> = mapping1.iter().map(|(_, b)| *b).collect();
 
@@ -44,6 +44,7 @@ pub fn deal_multislp2(
     num_of_constants: usize,
     targets: Vec<Term>,
     strategy: Strategy,
+    nr_pebbles: usize,
 ) -> Vec<(Pebble, Vec<Pebble>)> {
     // dbg!(PEBBLE_NUM);
 
@@ -51,7 +52,7 @@ pub fn deal_multislp2(
     let mut dag = multislp_to_dag(slp);
     let original_len = dag.len();
 
-    let mut alloc = Alloc::new(num_of_constants, targets, strategy);
+    let mut alloc = Alloc::new(num_of_constants, targets, strategy, nr_pebbles);
 
     let mut outdegs = make_outdegrees(&dag);
 
@@ -72,7 +73,7 @@ pub fn deal_multislp2(
         });
 
         let (target, _, _, _) = &reduced[0];
-        let children: &BTreeSet<Term> = dag.get(&target).unwrap();
+        let children: &BTreeSet<Term> = dag.get(target).unwrap();
 
         /*
          * Compute a visiting order of `children` by
@@ -90,16 +91,16 @@ pub fn deal_multislp2(
 
         for c in sorted {
             pebbles.push(alloc.get(c).unwrap());
-            alloc.access(&c);
+            alloc.access(c);
             let mut_ref = outdegs.get_mut(c).unwrap();
             *mut_ref -= 1;
             if *mut_ref == 0 {
                 alloc.try_release(c);
             }
         }
-        let pebble = alloc.assign(&target);
+        let pebble = alloc.assign(target);
         pebble_computation.push((pebble, pebbles));
-        dag.remove(&target);
+        dag.remove(target);
     }
 
     assert!(pebble_computation.len() == original_len);
@@ -53,7 +53,7 @@ fn build_syntax(valuation: &HashMap<usize, Vec<bool>>, goal: &[bool]) -> (Vec<Te
 
         for var in (0..valuation.keys().count()).rev() {
             let value = &valuation[&var];
-            let count = bitvec_distance(&value, &goal);
+            let count = bitvec_distance(value, &goal);
 
             if count < current_min {
                 which_var = Some(var);
@@ -190,7 +190,7 @@ fn repair(
             let left = slp.index_to_term(i);
             let right = slp.index_to_term(j);
 
-            let count = count_occurences(&left, &right, &program);
+            let count = count_occurences(&left, &right, program);
 
             let mut to_update = false;
 
@@ -381,7 +381,7 @@ pub fn execute_slp(slp: &SLP) -> SLP {
     let mut bitmatrix: Vec<Vec<bool>> = Vec::new();
 
     for i in 0..slp.num_of_variables() {
-        execute_slp_rec(&slp, i, &mut valuation);
+        execute_slp_rec(slp, i, &mut valuation);
         bitmatrix.push(valuation[&i].clone());
     }
 
@@ -392,7 +392,7 @@ pub fn execute_slp(slp: &SLP) -> SLP {
 pub fn evaluate_program(program: &[(Term, Term, Term)]) -> SLP {
     assert!(crate::fusion::is_ssa(&program.to_vec()));
     // let slp = programs_to_slp(&crate::fusion::slp_to_ssa(program));
-    let slp = programs_to_slp(&program);
+    let slp = programs_to_slp(program);
     execute_slp(&slp)
 }
 
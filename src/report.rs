@@ -0,0 +1,255 @@
+use crate::comparison::{self, AllStat, CompressStat};
+use crate::decoder;
+use crate::error::XorSlpError;
+use crate::pipeline::Options;
+use crate::slp::SLP;
+use crate::{rsv_bitmatrix, vandermonde, Parameter};
+use itertools::Itertools;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/*
+ * The aggregate tables of Section 7.3 computed in-process,
+ * which formerly were computed by reproducing/summarize_*.rb from the logs of
+ * --compare-compress and --all-stat.
+ *
+ * The programs are the encoding one and the decoding ones for every erasure pattern of
+ * nr_parity_block blocks, and every ratio is the average over the programs, in percent.
+ * With P the uncompressed program, Co(P) its XorRePair compression, Fu fusion and Dfs scheduling:
+ *   compression:   Repair(P)/P, XorRepair(P)/P on #XOR
+ *   memory access: Co(P)/P, Fu(P)/P, Fu(Co(P))/Co(P), Fu(Co(P))/P on #MemAcc
+ *   cache:         Co(P)/P, Fu(P)/P, Fu(Co(P))/Co(P), Dfs(Fu(Co(P)))/Co(P)
+ *                  on #Variables (NVar) and on the required cache capacity (CCap)
+ */
+
+/// The statistics of one program behind the report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgramStat {
+    pub compress: CompressStat,
+    pub without: AllStat, // without compression
+    pub with: AllStat,
+}
+
+// `None` for a trivial program, which is left out of the report
+pub fn program_stat(slp: &SLP, options: &Options) -> Result<Option<ProgramStat>, XorSlpError> {
+    let compress = comparison::compress_stat(slp)?;
+    let without = comparison::all_stat(slp, false, options)?;
+    let with = comparison::all_stat(slp, true, options)?;
+    Ok(match (compress, without, with) {
+        (Some(compress), Some(without), Some(with)) => Some(ProgramStat {
+            compress,
+            without,
+            with,
+        }),
+        _ => None,
+    })
+}
+
+/// The statistics of the encoding program (with no erased block)
+/// and the decoding programs of all the patterns of `nr_parity_block` erased blocks,
+/// computed by `nr_threads` worker threads.
+/// The scheduling uses `options.scheduler` and `options.block_size`.
+pub fn collect(
+    parameter: Parameter,
+    options: &Options,
+    nr_threads: usize,
+) -> Result<Vec<(Vec<usize>, ProgramStat)>, XorSlpError> {
+    parameter.check()?;
+    let enc = vandermonde::isa_rsv(parameter.nr_data_block, parameter.nr_parity_block);
+
+    let mut patterns = vec![Vec::new()];
+    patterns.extend(
+        (0..parameter.nr_data_block + parameter.nr_parity_block)
+            .combinations(parameter.nr_parity_block),
+    );
+
+    let next = AtomicUsize::new(0);
+    type Entry = Option<Result<Option<ProgramStat>, XorSlpError>>;
+    let entries: Mutex<Vec<Entry>> = Mutex::new(vec![None; patterns.len()]);
+    std::thread::scope(|s| {
+        for _ in 0..std::cmp::max(nr_threads, 1) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= patterns.len() {
+                    break;
+                }
+                let matrix = if patterns[i].is_empty() {
+                    Ok(enc.clone())
+                } else {
+                    decoder::decode_matrix(&enc, &patterns[i])
+                };
+                let entry = matrix.and_then(|matrix| {
+                    let bitmatrix = rsv_bitmatrix::matrix_to_bitmatrix(&matrix);
+                    program_stat(
+                        &SLP::build_from_bitmatrix_not_depending_variables(&bitmatrix),
+                        options,
+                    )
+                });
+                entries.lock().unwrap()[i] = Some(entry);
+            });
+        }
+    });
+
+    let mut stats = Vec::new();
+    for (erased, entry) in patterns.into_iter().zip(entries.into_inner().unwrap()) {
+        if let Some(stat) = entry.unwrap()? {
+            stats.push((erased, stat));
+        }
+    }
+    Ok(stats)
+}
+
+/// Ratios in percent, averaged over the programs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub nr_programs: usize,
+    pub compression: Vec<(&'static str, f64)>,
+    pub memacc: Vec<(&'static str, f64)>,
+    pub nr_variables: Vec<(&'static str, f64)>,
+    pub cache_capacity: Vec<(&'static str, f64)>,
+}
+
+fn average(ratios: &[(usize, usize)]) -> f64 {
+    let sum: f64 = ratios.iter().map(|(n, d)| *n as f64 / *d as f64).sum();
+    100.0 * sum / ratios.len() as f64
+}
+
+// the names of the ratios and their (numerator, denominator) for a program
+type Ratios = Vec<(&'static str, Box<dyn Fn(&ProgramStat) -> (usize, usize)>)>;
+
+fn averages(stats: &[ProgramStat], ratios: Ratios) -> Vec<(&'static str, f64)> {
+    ratios
+        .into_iter()
+        .map(|(name, ratio)| {
+            let ratios: Vec<(usize, usize)> = stats.iter().map(&ratio).collect();
+            (name, average(&ratios))
+        })
+        .collect()
+}
+
+// the cache table for a field of Stat
+fn cache_ratios(field: fn(&crate::stat::Stat) -> usize) -> Ratios {
+    vec![
+        (
+            "Co(P)/P",
+            Box::new(move |s| (field(&s.with.no_fusion), field(&s.without.no_fusion))),
+        ),
+        (
+            "Fu(P)/P",
+            Box::new(move |s| (field(&s.without.fusion), field(&s.without.no_fusion))),
+        ),
+        (
+            "Fu(Co(P))/Co(P)",
+            Box::new(move |s| (field(&s.with.fusion), field(&s.with.no_fusion))),
+        ),
+        (
+            "Dfs(Fu(Co(P)))/Co(P)",
+            Box::new(move |s| (field(&s.with.scheduled), field(&s.with.no_fusion))),
+        ),
+    ]
+}
+
+pub fn summarize(stats: &[ProgramStat]) -> Report {
+    let compression: Ratios = vec![
+        (
+            "Repair(P)/P",
+            Box::new(|s| (s.compress.repair.nr_xors, s.compress.no_comp.nr_xors)),
+        ),
+        (
+            "XorRepair(P)/P",
+            Box::new(|s| (s.compress.xor_repair.nr_xors, s.compress.no_comp.nr_xors)),
+        ),
+    ];
+
+    let memacc: Ratios = vec![
+        (
+            "Co(P)/P",
+            Box::new(|s| (s.with.no_fusion.nr_memacc, s.without.no_fusion.nr_memacc)),
+        ),
+        (
+            "Fu(P)/P",
+            Box::new(|s| (s.without.fusion.nr_memacc, s.without.no_fusion.nr_memacc)),
+        ),
+        (
+            "Fu(Co(P))/Co(P)",
+            Box::new(|s| (s.with.fusion.nr_memacc, s.with.no_fusion.nr_memacc)),
+        ),
+        (
+            "Fu(Co(P))/P",
+            Box::new(|s| (s.with.fusion.nr_memacc, s.without.no_fusion.nr_memacc)),
+        ),
+    ];
+
+    Report {
+        nr_programs: stats.len(),
+        compression: averages(stats, compression),
+        memacc: averages(stats, memacc),
+        nr_variables: averages(stats, cache_ratios(|s| s.nr_variables)),
+        cache_capacity: averages(stats, cache_ratios(|s| s.required_cache_capacity)),
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tables = [
+            ("Compression (#XOR)", &self.compression),
+            ("Memory accesses (#MemAcc)", &self.memacc),
+            ("Variables (NVar)", &self.nr_variables),
+            ("Cache capacity (CCap)", &self.cache_capacity),
+        ];
+
+        write!(f, "Averages over {} programs", self.nr_programs)?;
+        for (title, rows) in tables.iter() {
+            write!(f, "\n\n{}", title)?;
+            for (name, ratio) in rows.iter() {
+                write!(f, "\n  {} = {:.2} %", name, ratio)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_test() {
+        let parameter = Parameter {
+            nr_data_block: 3,
+            nr_parity_block: 2,
+        };
+        let stats = collect(parameter, &Options::default(), 2).unwrap();
+        // the encoding program and C(5, 2) decoding ones,
+        // except for the trivial one of [3, 4] which erases no data block
+        assert_eq!(stats.len(), 10);
+        assert!(stats[0].0.is_empty());
+        assert_eq!(stats[1].0, vec![0, 1]);
+        assert_eq!(stats[9].0, vec![2, 4]);
+        assert_eq!(stats, collect(parameter, &Options::default(), 1).unwrap());
+
+        let stats: Vec<ProgramStat> = stats.into_iter().map(|(_, s)| s).collect();
+        let report = summarize(&stats);
+        assert_eq!(report.nr_programs, 10);
+
+        let ratio =
+            |rows: &[(&str, f64)], name: &str| rows.iter().find(|(n, _)| *n == name).unwrap().1;
+        let expected = stats
+            .iter()
+            .map(|s| s.compress.xor_repair.nr_xors as f64 / s.compress.no_comp.nr_xors as f64)
+            .sum::<f64>()
+            * 100.0
+            / 10.0;
+        assert!((ratio(&report.compression, "XorRepair(P)/P") - expected).abs() < 1e-9);
+        // compression and fusion never increase the work
+        assert!(ratio(&report.compression, "XorRepair(P)/P") <= 100.0);
+        assert!(ratio(&report.memacc, "Fu(Co(P))/Co(P)") <= 100.0);
+
+        let text = report.to_string();
+        assert!(
+            text.starts_with("Averages over 10 programs\n\nCompression (#XOR)\n  Repair(P)/P = ")
+        );
+        assert!(text.contains("Cache capacity (CCap)\n  Co(P)/P = "));
+    }
+}
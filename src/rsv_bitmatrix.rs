@@ -171,7 +171,7 @@ mod tests {
     fn test_bitmatrix_rsv1() {
         let m = rsv(10, 4);
 
-        let remove_pattern = (0..14).into_iter().combinations(4);
+        let remove_pattern = (0..14).combinations(4);
 
         for remove in remove_pattern.into_iter() {
             let mut tmp = m.clone();
@@ -1,252 +1,130 @@
+use crate::error::XorSlpError;
+use crate::kernel::{Kernel, XorFn, XorIsZeroFn};
 use crate::reorder::Pebble;
 use crate::*;
 
-fn calc_addr(addrs: &[*const u8], idx_coeff: Pos, iter: usize) -> *const u8 {
-    unsafe {
-        addrs[(idx_coeff >> 1) as usize].add((idx_coeff & 1) as usize * iter * BLOCK_SIZE_PER_ITER)
-    }
+fn calc_addr(addrs: &[*const u8], idx_coeff: Pos, iter: usize, block_size: usize) -> *const u8 {
+    unsafe { addrs[(idx_coeff >> 1) as usize].add((idx_coeff & 1) as usize * iter * block_size) }
 }
 
-type Pos = u16;
+pub type Pos = u16;
 
 // For avoiding TLB missess
-unsafe fn prefetch_next(addr: &[*const u8], t: Pos, v: &[Pos], iter: usize) {
+#[cfg(target_arch = "x86_64")]
+unsafe fn prefetch_next(addr: &[*const u8], t: Pos, v: &[Pos], iter: usize, block_size: usize) {
     use std::arch::x86_64::*;
 
     const FETCH_TYPE: i32 = 2;
 
     for ptr in v {
-        let ptr = calc_addr(addr, *ptr, iter);
+        let ptr = calc_addr(addr, *ptr, iter, block_size);
         _mm_prefetch(ptr as *const i8, FETCH_TYPE);
         _mm_prefetch(ptr.add(64) as *const i8, FETCH_TYPE);
         _mm_prefetch(ptr.add(128) as *const i8, FETCH_TYPE);
         _mm_prefetch(ptr.add(192) as *const i8, FETCH_TYPE);
     }
 
-    let dst = calc_addr(addr, t, iter);
+    let dst = calc_addr(addr, t, iter, block_size);
     _mm_prefetch(dst as *const i8, FETCH_TYPE);
     _mm_prefetch(dst.add(64) as *const i8, FETCH_TYPE);
     _mm_prefetch(dst.add(128) as *const i8, FETCH_TYPE);
     _mm_prefetch(dst.add(192) as *const i8, FETCH_TYPE);
 }
 
-unsafe fn execute(addr: &[*const u8], t: Pos, v: &[Pos], iter: usize) {
-    #[cfg(feature = "64block")]
-    use crate::xor64::*;
-
-    #[cfg(not(feature = "64block"))]
-    use crate::xor::*;
-
-    let ptr_t = calc_addr(addr, t, iter) as *mut u8;
-
-    match v.len() {
-        2 => {
-            let ptr_v0 = calc_addr(addr, v[0], iter);
-            let ptr_v1 = calc_addr(addr, v[1], iter);
-            avx2_page_xor2(ptr_t, ptr_v0, ptr_v1);
-        }
-        3 => {
-            let ptr_v0 = calc_addr(addr, v[0], iter);
-            let ptr_v1 = calc_addr(addr, v[1], iter);
-            let ptr_v2 = calc_addr(addr, v[2], iter);
-            avx2_page_xor3(ptr_t, ptr_v0, ptr_v1, ptr_v2);
-        }
-        4 => {
-            let ptr_v0 = calc_addr(addr, v[0], iter);
-            let ptr_v1 = calc_addr(addr, v[1], iter);
-            let ptr_v2 = calc_addr(addr, v[2], iter);
-            let ptr_v3 = calc_addr(addr, v[3], iter);
-            avx2_page_xor4(ptr_t, ptr_v0, ptr_v1, ptr_v2, ptr_v3);
-        }
-        5 => {
-            let ptr_v0 = calc_addr(addr, v[0], iter);
-            let ptr_v1 = calc_addr(addr, v[1], iter);
-            let ptr_v2 = calc_addr(addr, v[2], iter);
-            let ptr_v3 = calc_addr(addr, v[3], iter);
-            let ptr_v4 = calc_addr(addr, v[4], iter);
-            avx2_page_xor5(ptr_t, ptr_v0, ptr_v1, ptr_v2, ptr_v3, ptr_v4);
-        }
-        6 => {
-            let ptr_v0 = calc_addr(addr, v[0], iter);
-            let ptr_v1 = calc_addr(addr, v[1], iter);
-            let ptr_v2 = calc_addr(addr, v[2], iter);
-            let ptr_v3 = calc_addr(addr, v[3], iter);
-            let ptr_v4 = calc_addr(addr, v[4], iter);
-            let ptr_v5 = calc_addr(addr, v[5], iter);
-            avx2_page_xor6(ptr_t, ptr_v0, ptr_v1, ptr_v2, ptr_v3, ptr_v4, ptr_v5);
-        }
-        7 => {
-            let ptr_v0 = calc_addr(addr, v[0], iter);
-            let ptr_v1 = calc_addr(addr, v[1], iter);
-            let ptr_v2 = calc_addr(addr, v[2], iter);
-            let ptr_v3 = calc_addr(addr, v[3], iter);
-            let ptr_v4 = calc_addr(addr, v[4], iter);
-            let ptr_v5 = calc_addr(addr, v[5], iter);
-            let ptr_v6 = calc_addr(addr, v[6], iter);
-            avx2_page_xor7(
-                ptr_t, ptr_v0, ptr_v1, ptr_v2, ptr_v3, ptr_v4, ptr_v5, ptr_v6,
-            );
-        }
-        8 => {
-            let ptr_v0 = calc_addr(addr, v[0], iter);
-            let ptr_v1 = calc_addr(addr, v[1], iter);
-            let ptr_v2 = calc_addr(addr, v[2], iter);
-            let ptr_v3 = calc_addr(addr, v[3], iter);
-            let ptr_v4 = calc_addr(addr, v[4], iter);
-            let ptr_v5 = calc_addr(addr, v[5], iter);
-            let ptr_v6 = calc_addr(addr, v[6], iter);
-            let ptr_v7 = calc_addr(addr, v[7], iter);
-            avx2_page_xor8(
-                ptr_t, ptr_v0, ptr_v1, ptr_v2, ptr_v3, ptr_v4, ptr_v5, ptr_v6, ptr_v7,
-            );
-        }
-        9 => {
-            let ptr_v0 = calc_addr(addr, v[0], iter);
-            let ptr_v1 = calc_addr(addr, v[1], iter);
-            let ptr_v2 = calc_addr(addr, v[2], iter);
-            let ptr_v3 = calc_addr(addr, v[3], iter);
-            let ptr_v4 = calc_addr(addr, v[4], iter);
-            let ptr_v5 = calc_addr(addr, v[5], iter);
-            let ptr_v6 = calc_addr(addr, v[6], iter);
-            let ptr_v7 = calc_addr(addr, v[7], iter);
-            let ptr_v8 = calc_addr(addr, v[8], iter);
-            avx2_page_xor9(
-                ptr_t, ptr_v0, ptr_v1, ptr_v2, ptr_v3, ptr_v4, ptr_v5, ptr_v6, ptr_v7, ptr_v8,
-            );
-        }
-        10 => {
-            let ptr_v0 = calc_addr(addr, v[0], iter);
-            let ptr_v1 = calc_addr(addr, v[1], iter);
-            let ptr_v2 = calc_addr(addr, v[2], iter);
-            let ptr_v3 = calc_addr(addr, v[3], iter);
-            let ptr_v4 = calc_addr(addr, v[4], iter);
-            let ptr_v5 = calc_addr(addr, v[5], iter);
-            let ptr_v6 = calc_addr(addr, v[6], iter);
-            let ptr_v7 = calc_addr(addr, v[7], iter);
-            let ptr_v8 = calc_addr(addr, v[8], iter);
-            let ptr_v9 = calc_addr(addr, v[9], iter);
-            avx2_page_xor10(
-                ptr_t, ptr_v0, ptr_v1, ptr_v2, ptr_v3, ptr_v4, ptr_v5, ptr_v6, ptr_v7, ptr_v8,
-                ptr_v9,
-            );
-        }
-        11 => {
-            let ptr_v0 = calc_addr(addr, v[0], iter);
-            let ptr_v1 = calc_addr(addr, v[1], iter);
-            let ptr_v2 = calc_addr(addr, v[2], iter);
-            let ptr_v3 = calc_addr(addr, v[3], iter);
-            let ptr_v4 = calc_addr(addr, v[4], iter);
-            let ptr_v5 = calc_addr(addr, v[5], iter);
-            let ptr_v6 = calc_addr(addr, v[6], iter);
-            let ptr_v7 = calc_addr(addr, v[7], iter);
-            let ptr_v8 = calc_addr(addr, v[8], iter);
-            let ptr_v9 = calc_addr(addr, v[9], iter);
-            let ptr_v10 = calc_addr(addr, v[10], iter);
-            avx2_page_xor11(
-                ptr_t, ptr_v0, ptr_v1, ptr_v2, ptr_v3, ptr_v4, ptr_v5, ptr_v6, ptr_v7, ptr_v8,
-                ptr_v9, ptr_v10,
-            );
-        }
-        12 => {
-            let ptr_v0 = calc_addr(addr, v[0], iter);
-            let ptr_v1 = calc_addr(addr, v[1], iter);
-            let ptr_v2 = calc_addr(addr, v[2], iter);
-            let ptr_v3 = calc_addr(addr, v[3], iter);
-            let ptr_v4 = calc_addr(addr, v[4], iter);
-            let ptr_v5 = calc_addr(addr, v[5], iter);
-            let ptr_v6 = calc_addr(addr, v[6], iter);
-            let ptr_v7 = calc_addr(addr, v[7], iter);
-            let ptr_v8 = calc_addr(addr, v[8], iter);
-            let ptr_v9 = calc_addr(addr, v[9], iter);
-            let ptr_v10 = calc_addr(addr, v[10], iter);
-            let ptr_v11 = calc_addr(addr, v[11], iter);
-            avx2_page_xor12(
-                ptr_t, ptr_v0, ptr_v1, ptr_v2, ptr_v3, ptr_v4, ptr_v5, ptr_v6, ptr_v7, ptr_v8,
-                ptr_v9, ptr_v10, ptr_v11,
-            );
-        }
-        13 => {
-            let ptr_v0 = calc_addr(addr, v[0], iter);
-            let ptr_v1 = calc_addr(addr, v[1], iter);
-            let ptr_v2 = calc_addr(addr, v[2], iter);
-            let ptr_v3 = calc_addr(addr, v[3], iter);
-            let ptr_v4 = calc_addr(addr, v[4], iter);
-            let ptr_v5 = calc_addr(addr, v[5], iter);
-            let ptr_v6 = calc_addr(addr, v[6], iter);
-            let ptr_v7 = calc_addr(addr, v[7], iter);
-            let ptr_v8 = calc_addr(addr, v[8], iter);
-            let ptr_v9 = calc_addr(addr, v[9], iter);
-            let ptr_v10 = calc_addr(addr, v[10], iter);
-            let ptr_v11 = calc_addr(addr, v[11], iter);
-            let ptr_v12 = calc_addr(addr, v[12], iter);
-            avx2_page_xor13(
-                ptr_t, ptr_v0, ptr_v1, ptr_v2, ptr_v3, ptr_v4, ptr_v5, ptr_v6, ptr_v7, ptr_v8,
-                ptr_v9, ptr_v10, ptr_v11, ptr_v12,
-            );
-        }
-        14 => {
-            let ptr_v0 = calc_addr(addr, v[0], iter);
-            let ptr_v1 = calc_addr(addr, v[1], iter);
-            let ptr_v2 = calc_addr(addr, v[2], iter);
-            let ptr_v3 = calc_addr(addr, v[3], iter);
-            let ptr_v4 = calc_addr(addr, v[4], iter);
-            let ptr_v5 = calc_addr(addr, v[5], iter);
-            let ptr_v6 = calc_addr(addr, v[6], iter);
-            let ptr_v7 = calc_addr(addr, v[7], iter);
-            let ptr_v8 = calc_addr(addr, v[8], iter);
-            let ptr_v9 = calc_addr(addr, v[9], iter);
-            let ptr_v10 = calc_addr(addr, v[10], iter);
-            let ptr_v11 = calc_addr(addr, v[11], iter);
-            let ptr_v12 = calc_addr(addr, v[12], iter);
-            let ptr_v13 = calc_addr(addr, v[13], iter);
-            avx2_page_xor14(
-                ptr_t, ptr_v0, ptr_v1, ptr_v2, ptr_v3, ptr_v4, ptr_v5, ptr_v6, ptr_v7, ptr_v8,
-                ptr_v9, ptr_v10, ptr_v11, ptr_v12, ptr_v13,
-            );
-        }
-        15 => {
-            let ptr_v0 = calc_addr(addr, v[0], iter);
-            let ptr_v1 = calc_addr(addr, v[1], iter);
-            let ptr_v2 = calc_addr(addr, v[2], iter);
-            let ptr_v3 = calc_addr(addr, v[3], iter);
-            let ptr_v4 = calc_addr(addr, v[4], iter);
-            let ptr_v5 = calc_addr(addr, v[5], iter);
-            let ptr_v6 = calc_addr(addr, v[6], iter);
-            let ptr_v7 = calc_addr(addr, v[7], iter);
-            let ptr_v8 = calc_addr(addr, v[8], iter);
-            let ptr_v9 = calc_addr(addr, v[9], iter);
-            let ptr_v10 = calc_addr(addr, v[10], iter);
-            let ptr_v11 = calc_addr(addr, v[11], iter);
-            let ptr_v12 = calc_addr(addr, v[12], iter);
-            let ptr_v13 = calc_addr(addr, v[13], iter);
-            let ptr_v14 = calc_addr(addr, v[14], iter);
-            avx2_page_xor15(
-                ptr_t, ptr_v0, ptr_v1, ptr_v2, ptr_v3, ptr_v4, ptr_v5, ptr_v6, ptr_v7, ptr_v8,
-                ptr_v9, ptr_v10, ptr_v11, ptr_v12, ptr_v13, ptr_v14,
-            );
-        }
-        _ => {
-            avx2_page_generic(
-                ptr_t,
-                &v.iter()
-                    .map(|a| calc_addr(addr, *a, iter))
-                    .collect::<Vec<_>>(),
-            );
+#[cfg(not(target_arch = "x86_64"))]
+unsafe fn prefetch_next(_: &[*const u8], _: Pos, _: &[Pos], _: usize, _: usize) {}
+
+// `dst = vs[0] ^ vs[1] ^ ...` for `len` bytes, where `len` is a multiple of 64
+unsafe fn xor_block(xor: XorFn, dst: *mut u8, vs: &[*const u8], len: usize) {
+    match vs.len() {
+        0 => std::ptr::write_bytes(dst, 0, len),
+        1 => std::ptr::copy(vs[0], dst, len),
+        _ => xor(dst, vs, len),
+    }
+}
+
+unsafe fn execute(
+    addr: &[*const u8],
+    t: Pos,
+    v: &[Pos],
+    iter: usize,
+    block_size: usize,
+    xor: XorFn,
+) {
+    let ptr_t = calc_addr(addr, t, iter, block_size) as *mut u8;
+
+    let mut buf = [std::ptr::null(); 16];
+    if v.len() <= buf.len() {
+        for (b, p) in buf.iter_mut().zip(v) {
+            *b = calc_addr(addr, *p, iter, block_size);
+        }
+        xor_block(xor, ptr_t, &buf[..v.len()], block_size);
+    } else {
+        let vs: Vec<_> = v
+            .iter()
+            .map(|p| calc_addr(addr, *p, iter, block_size))
+            .collect();
+        xor_block(xor, ptr_t, &vs, block_size);
+    }
+}
+
+// `targets[i] = Some(j)` turns the `i`-th step into a test whether the XOR of its sources is zero;
+// nothing is stored, and a nonzero result sets `mismatches[j]`.
+struct Checks<'a> {
+    targets: &'a [Option<usize>],
+    is_zero: XorIsZeroFn,
+    mismatches: &'a mut [bool],
+}
+
+unsafe fn check(
+    addr: &[*const u8],
+    v: &[Pos],
+    iter: usize,
+    block_size: usize,
+    is_zero: XorIsZeroFn,
+) -> bool {
+    let mut buf = [std::ptr::null(); 16];
+    if v.is_empty() {
+        true
+    } else if v.len() <= buf.len() {
+        for (b, p) in buf.iter_mut().zip(v) {
+            *b = calc_addr(addr, *p, iter, block_size);
         }
+        is_zero(&buf[..v.len()], block_size)
+    } else {
+        let vs: Vec<_> = v
+            .iter()
+            .map(|p| calc_addr(addr, *p, iter, block_size))
+            .collect();
+        is_zero(&vs, block_size)
     }
 }
 
-fn run(addrs: &[*const u8], seq: &[(Pos, Vec<Pos>)], iter: usize) {
+fn run(
+    addrs: &[*const u8],
+    seq: &[(Pos, Vec<Pos>)],
+    iter: usize,
+    block_size: usize,
+    xor: XorFn,
+    mut checks: Option<&mut Checks>,
+) {
     let l = seq.len();
-    for i in 0..l - 1 {
-        let (t, v) = &seq[i + 1];
-        unsafe { prefetch_next(addrs, *t, v, iter) };
+    for i in 0..l {
+        if i + 1 < l {
+            let (t, v) = &seq[i + 1];
+            unsafe { prefetch_next(addrs, *t, v, iter, block_size) };
+        }
 
         let (t, v) = &seq[i];
-        unsafe { execute(addrs, *t, v, iter) };
+        match checks.as_deref_mut() {
+            Some(c) if c.targets[i].is_some() => {
+                if !unsafe { check(addrs, v, iter, block_size, c.is_zero) } {
+                    c.mismatches[c.targets[i].unwrap()] = true;
+                }
+            }
+            _ => unsafe { execute(addrs, *t, v, iter, block_size, xor) },
+        }
     }
-    let (t, v) = &seq[l - 1];
-    unsafe { execute(addrs, *t, v, iter) };
 }
 
 pub fn required_pebbles(seq: &[(Pebble, &[Pebble])]) -> usize {
@@ -292,6 +170,11 @@ impl PageAlignedArray {
         }
     }
 
+    /// `new` reporting a failure as `RunError::OutOfMemory`
+    pub fn try_new(size: usize) -> Result<Self, RunError> {
+        Self::new(size).ok_or(RunError::OutOfMemory(size))
+    }
+
     pub fn head(&self) -> *const u8 {
         self.ptr
     }
@@ -311,7 +194,7 @@ impl PageAlignedArray {
 
         let mut v = Vec::new();
         assert!(
-            self.size % height == 0,
+            self.size.is_multiple_of(height),
             "size = {}, height = {}",
             self.size,
             height
@@ -325,6 +208,15 @@ impl PageAlignedArray {
         }
         v
     }
+
+    pub fn split_mut(&mut self, height: usize) -> Vec<&mut [u8]> {
+        if height == 0 {
+            return Vec::new();
+        }
+
+        let width = self.size / height;
+        self.as_mut_slice().chunks_exact_mut(width).collect()
+    }
 }
 
 impl Drop for PageAlignedArray {
@@ -334,6 +226,15 @@ impl Drop for PageAlignedArray {
 }
 
 pub fn compile(p: Parameter, program: &[(Pebble, &[Pebble])]) -> Vec<(Pos, Vec<Pos>)> {
+    compile_planes(p, 8, program)
+}
+
+/// `compile` for shards of `nr_planes` bit-planes.
+pub fn compile_planes(
+    p: Parameter,
+    nr_planes: usize,
+    program: &[(Pebble, &[Pebble])],
+) -> Vec<(Pos, Vec<Pos>)> {
     let mut new_program = Vec::new();
 
     let aux = |v: &Pebble| -> Pos {
@@ -343,10 +244,10 @@ pub fn compile(p: Parameter, program: &[(Pebble, &[Pebble])]) -> Vec<(Pos, Vec<P
             dst = (idx, 1u8);
         } else {
             let idx = v.from_var().unwrap();
-            if idx < p.nr_parity_block * 8 {
-                dst = (8 * p.nr_data_block + idx, 1u8);
+            if idx < p.nr_parity_block * nr_planes {
+                dst = (nr_planes * p.nr_data_block + idx, 1u8);
             } else {
-                dst = (8 * p.nr_data_block + idx, 0u8);
+                dst = (nr_planes * p.nr_data_block + idx, 0u8);
             }
         }
         let (a, b) = dst;
@@ -363,6 +264,433 @@ pub fn compile(p: Parameter, program: &[(Pebble, &[Pebble])]) -> Vec<(Pos, Vec<P
     new_program
 }
 
+/// A compiled program together with the shape of the buffers it works on.
+///
+/// The buffers are laid out as `[inputs, outputs, tmps]` in the same way as
+/// `combine_constant_target_tmp`.
+#[derive(Clone, Debug)]
+pub struct Program {
+    nr_inputs: usize,
+    nr_outputs: usize,
+    nr_tmps: usize,
+    block_size: usize,
+    kernel: Kernel,
+    nr_threads: usize,
+    nr_planes: usize,
+    code: Vec<(Pos, Vec<Pos>)>,
+}
+
+fn to_pos(idx: usize, coeff: u8) -> Pos {
+    ((idx as u16) << 1) | ((coeff & 1) as u16)
+}
+
+impl Program {
+    /// `targets[i]` is the pebble whose value is stored to the `i`-th output:
+    /// `Some(Pebble::Var(_))` is computed by `program`,
+    /// `Some(Pebble::Const(_))` is a copy of an input, and `None` is zero.
+    ///
+    /// Each iteration processes `block_size` bytes of every input and output.
+    /// The XOR kernels are the fastest ones the running CPU supports (see `Kernel::detect`).
+    pub fn compile(
+        nr_inputs: usize,
+        targets: &[Option<Pebble>],
+        program: &[(Pebble, Vec<Pebble>)],
+        block_size: usize,
+    ) -> Result<Self, XorSlpError> {
+        Self::compile_with(nr_inputs, targets, program, block_size, false)
+    }
+
+    /// `compile` for a program XORing `targets[i]` into the `i`-th output instead of storing it,
+    /// so that the outputs are updated in place; `None` leaves the output as it is.
+    ///
+    /// Every variable is placed on the tmp area,
+    /// and each output is read and written only by its own last step.
+    pub fn compile_accumulate(
+        nr_inputs: usize,
+        targets: &[Option<Pebble>],
+        program: &[(Pebble, Vec<Pebble>)],
+        block_size: usize,
+    ) -> Result<Self, XorSlpError> {
+        Self::compile_with(nr_inputs, targets, program, block_size, true)
+    }
+
+    fn compile_with(
+        nr_inputs: usize,
+        targets: &[Option<Pebble>],
+        program: &[(Pebble, Vec<Pebble>)],
+        block_size: usize,
+        accumulate: bool,
+    ) -> Result<Self, XorSlpError> {
+        check_block_size(block_size).map_err(XorSlpError::InvalidParameter)?;
+
+        let nr_outputs = targets.len();
+
+        // a variable is placed on the first output requiring it
+        let mut outputs: BTreeMap<usize, usize> = BTreeMap::new();
+        for (i, t) in targets.iter().enumerate() {
+            if let Some(Pebble::Var(v)) = t {
+                if !accumulate {
+                    outputs.entry(*v).or_insert(i);
+                }
+            }
+        }
+
+        // the other variables are placed on the tmp area
+        let mut tmps: BTreeMap<usize, usize> = BTreeMap::new();
+        for (t, vars) in program {
+            for p in std::iter::once(t).chain(vars.iter()) {
+                if let Pebble::Var(v) = p {
+                    if !outputs.contains_key(v) && !tmps.contains_key(v) {
+                        let slot = tmps.len();
+                        tmps.insert(*v, slot);
+                    }
+                }
+            }
+        }
+
+        let aux = |p: &Pebble| -> Pos {
+            match p {
+                Pebble::Const(c) => to_pos(*c, 1),
+                Pebble::Var(v) => {
+                    if let Some(o) = outputs.get(v) {
+                        to_pos(nr_inputs + o, 1)
+                    } else {
+                        to_pos(nr_inputs + nr_outputs + tmps[v], 0)
+                    }
+                }
+            }
+        };
+
+        let mut code: Vec<(Pos, Vec<Pos>)> = program
+            .iter()
+            .map(|(t, vars)| (aux(t), vars.iter().map(aux).collect()))
+            .collect();
+
+        for (i, t) in targets.iter().enumerate() {
+            let dst = to_pos(nr_inputs + i, 1);
+            match t {
+                // the XOR kernels allow the target among the sources
+                Some(p) if accumulate => code.push((dst, vec![aux(p), dst])),
+                None if accumulate => {}
+                Some(Pebble::Var(v)) if outputs[v] == i => {}
+                Some(p) => code.push((dst, vec![aux(p)])),
+                None => code.push((dst, Vec::new())),
+            }
+        }
+
+        if nr_inputs + nr_outputs + tmps.len() > (Pos::MAX >> 1) as usize {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "{} pebbles can not be addressed",
+                nr_inputs + nr_outputs + tmps.len()
+            )));
+        }
+
+        Ok(Program {
+            nr_inputs,
+            nr_outputs,
+            nr_tmps: tmps.len(),
+            block_size,
+            kernel: Kernel::detect(),
+            nr_threads: 1,
+            nr_planes: 8,
+            code,
+        })
+    }
+
+    /// Split the iterations over `nr_threads` worker threads (see `run_slices_parallel`).
+    pub fn with_threads(mut self, nr_threads: usize) -> Self {
+        self.nr_threads = std::cmp::max(nr_threads, 1);
+        self
+    }
+
+    /// Use `kernel` instead of the detected one.
+    pub fn with_kernel(mut self, kernel: Kernel) -> Result<Self, XorSlpError> {
+        if !kernel.is_available() {
+            return Err(RunError::UnavailableKernel(kernel).into());
+        }
+        self.kernel = kernel;
+        Ok(self)
+    }
+
+    /// Treat each shard as `nr_planes` bit-planes instead of 8 (see `run_on_shards`),
+    /// e.g., 4 for a program compiled from a GF(2^4) bitmatrix.
+    pub fn with_planes(mut self, nr_planes: usize) -> Result<Self, XorSlpError> {
+        if nr_planes == 0
+            || !self.nr_inputs.is_multiple_of(nr_planes)
+            || !self.nr_outputs.is_multiple_of(nr_planes)
+        {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "{} inputs and {} outputs can not be split into shards of {} bit-planes",
+                self.nr_inputs, self.nr_outputs, nr_planes
+            )));
+        }
+        self.nr_planes = nr_planes;
+        Ok(self)
+    }
+
+    pub fn nr_inputs(&self) -> usize {
+        self.nr_inputs
+    }
+
+    pub fn nr_outputs(&self) -> usize {
+        self.nr_outputs
+    }
+
+    pub fn nr_tmps(&self) -> usize {
+        self.nr_tmps
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub fn kernel(&self) -> Kernel {
+        self.kernel
+    }
+
+    pub fn nr_threads(&self) -> usize {
+        self.nr_threads
+    }
+
+    pub fn nr_planes(&self) -> usize {
+        self.nr_planes
+    }
+
+    pub fn code(&self) -> &[(Pos, Vec<Pos>)] {
+        &self.code
+    }
+
+    /// Run the program on bit-planes laid out as `[inputs, outputs]`.
+    ///
+    /// The tmp areas are allocated internally.
+    pub fn execute(&self, inputs: &[&[u8]], outputs: &mut [&mut [u8]]) -> Result<(), RunError> {
+        if inputs.len() != self.nr_inputs {
+            return Err(RunError::WrongNumberOfInputs {
+                expected: self.nr_inputs,
+                actual: inputs.len(),
+            });
+        }
+        if outputs.len() != self.nr_outputs {
+            return Err(RunError::WrongNumberOfOutputs {
+                expected: self.nr_outputs,
+                actual: outputs.len(),
+            });
+        }
+
+        run_slices_parallel(
+            &self.code,
+            self.block_size,
+            self.kernel,
+            self.nr_tmps,
+            self.nr_threads,
+            inputs,
+            outputs,
+        )
+    }
+
+    /// Run the program on shards, each of which consists of `nr_planes` bit-planes
+    /// of the same width (8 unless set by `with_planes`).
+    ///
+    /// All the shards must have the same length, which is a multiple of `nr_planes`.
+    /// Shards of `nr_planes * block_size * n` bytes which are 32-byte aligned run fastest.
+    pub fn run_on_shards(
+        &self,
+        inputs: &[&[u8]],
+        outputs: &mut [&mut [u8]],
+    ) -> Result<(), RunError> {
+        let len = inputs.first().map_or(0, |s| s.len());
+        if inputs.iter().any(|s| s.len() != len) || outputs.iter().any(|s| s.len() != len) {
+            return Err(RunError::LengthMismatch);
+        }
+        let w = self.nr_planes;
+        if !len.is_multiple_of(w) {
+            return Err(RunError::InvalidLength(len));
+        }
+
+        if len == 0 {
+            let mut empty: Vec<&mut [u8]> = (0..outputs.len() * w).map(|_| &mut [][..]).collect();
+            return self.execute(&vec![&[][..]; inputs.len() * w], &mut empty);
+        }
+
+        let width = len / w;
+        let input_planes: Vec<&[u8]> = inputs.iter().flat_map(|s| s.chunks(width)).collect();
+        let mut output_planes: Vec<&mut [u8]> = outputs
+            .iter_mut()
+            .flat_map(|s| s.chunks_mut(width))
+            .collect();
+
+        self.execute(&input_planes, &mut output_planes)
+    }
+}
+
+/// A program testing which outputs of a `Program` are nonzero, without storing them.
+///
+/// An output which is written once and never read is tested by a fused XOR-and-test step.
+/// The other outputs are computed into blocks reused every iteration and tested afterwards.
+#[derive(Clone, Debug)]
+pub struct CheckProgram {
+    nr_inputs: usize,
+    nr_outputs: usize,
+    nr_tmps: usize,
+    block_size: usize,
+    kernel: Kernel,
+    nr_planes: usize,
+    code: Vec<(Pos, Vec<Pos>)>,
+    checks: Vec<Option<usize>>,
+}
+
+impl CheckProgram {
+    pub fn new(program: &Program) -> Self {
+        let nr_inputs = program.nr_inputs;
+        let nr_outputs = program.nr_outputs;
+
+        let output = |p: Pos| {
+            let idx = (p >> 1) as usize;
+            if nr_inputs <= idx && idx < nr_inputs + nr_outputs {
+                Some(idx - nr_inputs)
+            } else {
+                None
+            }
+        };
+        // the outputs do not move between iterations
+        let rebase = |p: &Pos| if output(*p).is_some() { p & !1 } else { *p };
+
+        let mut writes = vec![0; nr_outputs];
+        let mut read = vec![false; nr_outputs];
+        for (t, vars) in &program.code {
+            if let Some(o) = output(*t) {
+                writes[o] += 1;
+            }
+            for o in vars.iter().filter_map(|v| output(*v)) {
+                read[o] = true;
+            }
+        }
+        let fused = |o: usize| writes[o] == 1 && !read[o];
+
+        let mut code = Vec::new();
+        let mut checks = Vec::new();
+        for (t, vars) in &program.code {
+            code.push((rebase(t), vars.iter().map(rebase).collect()));
+            checks.push(output(*t).filter(|o| fused(*o)));
+        }
+        for o in (0..nr_outputs).filter(|o| !fused(*o)) {
+            let p = to_pos(nr_inputs + o, 0);
+            code.push((p, vec![p]));
+            checks.push(Some(o));
+        }
+
+        CheckProgram {
+            nr_inputs,
+            nr_outputs,
+            nr_tmps: program.nr_tmps,
+            block_size: program.block_size,
+            kernel: program.kernel,
+            nr_planes: program.nr_planes,
+            code,
+            checks,
+        }
+    }
+
+    /// The outputs which are nonzero for the bit-planes `inputs`, in ascending order.
+    pub fn execute(&self, inputs: &[&[u8]]) -> Result<Vec<usize>, RunError> {
+        if inputs.len() != self.nr_inputs {
+            return Err(RunError::WrongNumberOfInputs {
+                expected: self.nr_inputs,
+                actual: inputs.len(),
+            });
+        }
+
+        // the blocks for the outputs followed by the tmps
+        let nr_blocks = std::cmp::max(self.nr_outputs + self.nr_tmps, 1);
+        let mut tmp = PageAlignedArray::try_new(self.block_size * nr_blocks)?;
+        tmp.as_mut_slice().fill(0);
+        let mut tmps = tmp.split_mut(nr_blocks);
+
+        let mut mismatches = vec![false; self.nr_outputs];
+        let mut checks = Checks {
+            targets: &self.checks,
+            is_zero: self.kernel.xor_is_zero_fn(),
+            mismatches: &mut mismatches,
+        };
+        run_slices_with(
+            &self.code,
+            self.block_size,
+            self.kernel,
+            inputs,
+            &mut [],
+            &mut tmps[..self.nr_outputs + self.nr_tmps],
+            Some(&mut checks),
+        )?;
+
+        Ok((0..self.nr_outputs).filter(|o| mismatches[*o]).collect())
+    }
+
+    /// `execute` on shards, each of which consists of the bit-planes of the program
+    /// (see `Program::run_on_shards`).
+    pub fn run_on_shards(&self, inputs: &[&[u8]]) -> Result<Vec<usize>, RunError> {
+        let len = inputs.first().map_or(0, |s| s.len());
+        if inputs.iter().any(|s| s.len() != len) {
+            return Err(RunError::LengthMismatch);
+        }
+        let w = self.nr_planes;
+        if !len.is_multiple_of(w) {
+            return Err(RunError::InvalidLength(len));
+        }
+
+        if len == 0 {
+            return self.execute(&vec![&[][..]; inputs.len() * w]);
+        }
+
+        let input_planes: Vec<&[u8]> = inputs.iter().flat_map(|s| s.chunks(len / w)).collect();
+        self.execute(&input_planes)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunError {
+    WrongNumberOfInputs { expected: usize, actual: usize },
+    WrongNumberOfOutputs { expected: usize, actual: usize },
+    // inputs and outputs have different lengths
+    LengthMismatch,
+    // the length of shards is not a multiple of the number of bit-planes
+    InvalidLength(usize),
+    // a tmp buffer is not 32-byte aligned or smaller than the block size
+    InvalidTmp,
+    // the block size is not a positive multiple of 64
+    InvalidBlockSize(usize),
+    // the program refers to a buffer which does not exist
+    PebbleOutOfRange(Pos),
+    // the program writes to an input buffer
+    WriteToInput(Pos),
+    // the CPU does not support the instructions of the kernel
+    UnavailableKernel(Kernel),
+    // a work area of the given bytes can not be allocated
+    OutOfMemory(usize),
+}
+
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RunError::WrongNumberOfInputs { expected, actual } => {
+                write!(f, "expected {} input buffers, got {}", expected, actual)
+            }
+            RunError::WrongNumberOfOutputs { expected, actual } => {
+                write!(f, "expected {} output buffers, got {}", expected, actual)
+            }
+            RunError::LengthMismatch => write!(f, "buffers have different lengths"),
+            RunError::InvalidLength(len) => write!(f, "invalid buffer length {}", len),
+            RunError::InvalidTmp => write!(f, "tmp buffers must be 32-byte aligned blocks"),
+            RunError::InvalidBlockSize(size) => write!(f, "invalid block size {}", size),
+            RunError::PebbleOutOfRange(p) => write!(f, "no buffer for pebble {}", p >> 1),
+            RunError::WriteToInput(p) => write!(f, "pebble {} is an input", p >> 1),
+            RunError::UnavailableKernel(k) => write!(f, "{} is not supported by this CPU", k),
+            RunError::OutOfMemory(size) => write!(f, "failed to allocate {} bytes", size),
+        }
+    }
+}
+
+impl std::error::Error for RunError {}
+
 pub fn estimate_compile(program: &[(Pebble, &[Pebble])]) -> Vec<(Pos, Vec<Pos>)> {
     let mut new_program = Vec::new();
 
@@ -377,8 +705,8 @@ pub fn estimate_compile(program: &[(Pebble, &[Pebble])]) -> Vec<(Pos, Vec<Pos>)>
 
 pub fn combine_constant_target_tmp(
     constants: &[&[u8]],
-    to_store: &[&[u8]],
-    tmp: &[&[u8]],
+    to_store: &mut [&mut [u8]],
+    tmp: &mut [&mut [u8]],
 ) -> Vec<*const u8> {
     let mut v: Vec<*const u8> = Vec::new();
 
@@ -386,17 +714,543 @@ pub fn combine_constant_target_tmp(
         v.push(p.as_ptr());
     }
     for p in to_store {
-        v.push(p.as_ptr());
+        v.push(p.as_mut_ptr() as *const u8);
     }
     for p in tmp {
-        v.push(p.as_ptr());
+        v.push(p.as_mut_ptr() as *const u8);
     }
 
     v
 }
 
-pub fn run_program(all_buffers: &[*const u8], iteration: usize, program: &[(Pos, Vec<Pos>)]) {
+/// Run a compiled program on the buffers `[inputs, outputs, tmps]`.
+///
+/// Inputs and outputs are accessed with coefficient 1 and must have the same length.
+/// Tmps are accessed with coefficient 0, and need `block_size` bytes and 32-byte alignment.
+///
+/// Inputs and outputs of any length and alignment are accepted:
+/// whole iterations run in place if every buffer is 32-byte aligned,
+/// and the rest is staged through an aligned zero-padded area one iteration at a time.
+pub fn run_slices(
+    program: &[(Pos, Vec<Pos>)],
+    block_size: usize,
+    kernel: Kernel,
+    inputs: &[&[u8]],
+    outputs: &mut [&mut [u8]],
+    tmps: &mut [&mut [u8]],
+) -> Result<(), RunError> {
+    run_slices_with(program, block_size, kernel, inputs, outputs, tmps, None)
+}
+
+fn run_slices_with(
+    program: &[(Pos, Vec<Pos>)],
+    block_size: usize,
+    kernel: Kernel,
+    inputs: &[&[u8]],
+    outputs: &mut [&mut [u8]],
+    tmps: &mut [&mut [u8]],
+    mut checks: Option<&mut Checks>,
+) -> Result<(), RunError> {
+    let width = inputs
+        .first()
+        .map(|s| s.len())
+        .or_else(|| outputs.first().map(|s| s.len()))
+        .unwrap_or(0);
+    if inputs.iter().any(|s| s.len() != width) || outputs.iter().any(|s| s.len() != width) {
+        return Err(RunError::LengthMismatch);
+    }
+
+    if check_block_size(block_size).is_err() {
+        return Err(RunError::InvalidBlockSize(block_size));
+    }
+
+    if !kernel.is_available() {
+        return Err(RunError::UnavailableKernel(kernel));
+    }
+
+    let aligned = |p: *const u8| (p as usize).is_multiple_of(32);
+    if !tmps
+        .iter()
+        .all(|s| aligned(s.as_ptr()) && s.len() >= block_size)
+    {
+        return Err(RunError::InvalidTmp);
+    }
+
+    let nr_io = inputs.len() + outputs.len();
+    for (t, vars) in program {
+        for p in std::iter::once(t).chain(vars.iter()) {
+            let idx = (p >> 1) as usize;
+            // tmps do not move between iterations
+            if idx >= nr_io + tmps.len() || (idx >= nr_io && p & 1 == 1) {
+                return Err(RunError::PebbleOutOfRange(*p));
+            }
+        }
+        if ((t >> 1) as usize) < inputs.len() {
+            return Err(RunError::WriteToInput(*t));
+        }
+    }
+
+    if program.is_empty() || width == 0 {
+        return Ok(());
+    }
+
+    let in_place =
+        inputs.iter().all(|s| aligned(s.as_ptr())) && outputs.iter().all(|s| aligned(s.as_ptr()));
+    let done = if in_place {
+        width - width % block_size
+    } else {
+        0
+    };
+
+    if done > 0 {
+        let addrs = combine_constant_target_tmp(inputs, outputs, tmps);
+        unsafe {
+            run_program_with(
+                &addrs,
+                done / block_size,
+                block_size,
+                kernel,
+                program,
+                checks.as_deref_mut(),
+            )
+        };
+    }
+
+    if done < width {
+        let mut stage = PageAlignedArray::try_new(nr_io * block_size)?;
+        stage.as_mut_slice().fill(0);
+
+        for offset in (done..width).step_by(block_size) {
+            let size = std::cmp::min(block_size, width - offset);
+
+            // the outputs are staged too, since a program may read them (see `Program::compile_accumulate`)
+            let mut slots = stage.split_mut(nr_io);
+            for (slot, buffer) in slots.iter_mut().zip(
+                inputs
+                    .iter()
+                    .map(|s| &s[..])
+                    .chain(outputs.iter().map(|s| &s[..])),
+            ) {
+                slot[..size].copy_from_slice(&buffer[offset..offset + size]);
+                // no garbage from the previous iteration
+                slot[size..].fill(0);
+            }
+
+            let (ins, outs) = slots.split_at_mut(inputs.len());
+            let ins: Vec<&[u8]> = ins.iter().map(|s| &**s).collect();
+            let addrs = combine_constant_target_tmp(&ins, outs, tmps);
+            unsafe {
+                run_program_with(
+                    &addrs,
+                    1,
+                    block_size,
+                    kernel,
+                    program,
+                    checks.as_deref_mut(),
+                )
+            };
+
+            for (output, slot) in outputs.iter_mut().zip(outs.iter()) {
+                output[offset..offset + size].copy_from_slice(&slot[..size]);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `run_slices` splitting the iterations over `nr_threads` worker threads.
+///
+/// Each worker processes a contiguous range of whole iterations
+/// with its own `nr_tmps` tmp buffers of `block_size` bytes, which are allocated here.
+pub fn run_slices_parallel(
+    program: &[(Pos, Vec<Pos>)],
+    block_size: usize,
+    kernel: Kernel,
+    nr_tmps: usize,
+    nr_threads: usize,
+    inputs: &[&[u8]],
+    outputs: &mut [&mut [u8]],
+) -> Result<(), RunError> {
+    let width = inputs
+        .first()
+        .map(|s| s.len())
+        .or_else(|| outputs.first().map(|s| s.len()))
+        .unwrap_or(0);
+    if inputs.iter().any(|s| s.len() != width) || outputs.iter().any(|s| s.len() != width) {
+        return Err(RunError::LengthMismatch);
+    }
+    if check_block_size(block_size).is_err() {
+        return Err(RunError::InvalidBlockSize(block_size));
+    }
+
+    let run_part = |ins: &[&[u8]], outs: &mut [&mut [u8]]| {
+        let mut tmp =
+            PageAlignedArray::try_new(block_size.saturating_mul(std::cmp::max(nr_tmps, 1)))?;
+        let mut tmps = tmp.split_mut(std::cmp::max(nr_tmps, 1));
+        run_slices(program, block_size, kernel, ins, outs, &mut tmps[..nr_tmps])
+    };
+
+    let nr_iters = width.div_ceil(block_size);
+    if nr_threads <= 1 || nr_iters <= 1 {
+        return run_part(inputs, outputs);
+    }
+
+    // a multiple of the block size keeps the alignment of every part
+    let chunk = nr_iters.div_ceil(nr_threads) * block_size;
+    let nr_parts = width.div_ceil(chunk);
+    let mut part_inputs: Vec<Vec<&[u8]>> = vec![Vec::new(); nr_parts];
+    let mut part_outputs: Vec<Vec<&mut [u8]>> = (0..nr_parts).map(|_| Vec::new()).collect();
+    for input in inputs {
+        for (part, c) in part_inputs.iter_mut().zip(input.chunks(chunk)) {
+            part.push(c);
+        }
+    }
+    for output in outputs.iter_mut() {
+        for (part, c) in part_outputs.iter_mut().zip(output.chunks_mut(chunk)) {
+            part.push(c);
+        }
+    }
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = part_inputs
+            .into_iter()
+            .zip(part_outputs)
+            .map(|(ins, mut outs)| scope.spawn(move || run_part(&ins, &mut outs)))
+            .collect();
+        workers
+            .into_iter()
+            .try_for_each(|w| w.join().expect("a worker thread panicked"))
+    })
+}
+
+/// # Safety
+///
+/// Every address `program` accesses must be valid for `iteration` iterations,
+/// and the targets must be writable. `kernel` must be available on the running CPU.
+/// Use `run_slices` for the checked version.
+pub unsafe fn run_program(
+    all_buffers: &[*const u8],
+    iteration: usize,
+    block_size: usize,
+    kernel: Kernel,
+    program: &[(Pos, Vec<Pos>)],
+) {
+    run_program_with(all_buffers, iteration, block_size, kernel, program, None)
+}
+
+unsafe fn run_program_with(
+    all_buffers: &[*const u8],
+    iteration: usize,
+    block_size: usize,
+    kernel: Kernel,
+    program: &[(Pos, Vec<Pos>)],
+    mut checks: Option<&mut Checks>,
+) {
+    if program.is_empty() {
+        return;
+    }
+
+    let xor = kernel.xor_fn();
     for i in 0..iteration {
-        run(all_buffers, &program, i);
+        run(
+            all_buffers,
+            program,
+            i,
+            block_size,
+            xor,
+            checks.as_deref_mut(),
+        );
+    }
+}
+
+/// The XOR kernels process 64 bytes at once.
+pub fn check_block_size(block_size: usize) -> Result<(), String> {
+    if block_size == 0 || !block_size.is_multiple_of(64) {
+        Err(format!(
+            "block size {} is not a positive multiple of 64",
+            block_size
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // out0 = in0 ^ in1; out1 = in1 (via a tmp)
+    fn sample_program() -> Vec<(Pos, Vec<Pos>)> {
+        vec![
+            (to_pos(2, 1), vec![to_pos(0, 1), to_pos(1, 1)]),
+            (to_pos(4, 0), vec![to_pos(1, 1)]),
+            (to_pos(3, 1), vec![to_pos(4, 0)]),
+        ]
+    }
+
+    // out0 = in0 ^ in1 ^ ... ^ in(n-1); out1 = in1 (via a tmp)
+    // on buffers of `width` bytes starting at `offset`
+    fn check(n: usize, width: usize, offset: usize, block_size: usize, kernel: Kernel) {
+        let program: Vec<(Pos, Vec<Pos>)> = vec![
+            (to_pos(n, 1), (0..n).map(|i| to_pos(i, 1)).collect()),
+            (to_pos(n + 2, 0), vec![to_pos(1, 1)]),
+            (to_pos(n + 1, 1), vec![to_pos(n + 2, 0)]),
+        ];
+
+        let mut input = vec![0u8; (width + offset) * n];
+        fill_by_random(&mut input);
+        let mut output = vec![0u8; (width + offset) * 2];
+        let mut tmp = PageAlignedArray::new(block_size).unwrap();
+
+        let inputs: Vec<&[u8]> = input.chunks(width + offset).map(|s| &s[offset..]).collect();
+        let mut outputs: Vec<&mut [u8]> = output
+            .chunks_mut(width + offset)
+            .map(|s| &mut s[offset..])
+            .collect();
+
+        run_slices(
+            &program,
+            block_size,
+            kernel,
+            &inputs,
+            &mut outputs,
+            &mut tmp.split_mut(1),
+        )
+        .unwrap();
+        for i in 0..width {
+            let expected = inputs.iter().fold(0, |acc, s| acc ^ s[i]);
+            assert_eq!(outputs[0][i], expected);
+        }
+        assert!(outputs[1][..] == inputs[1][..]);
+    }
+
+    #[test]
+    fn run_slices_test() {
+        let program = sample_program();
+
+        let width = BLOCK_SIZE_PER_ITER * 2;
+        let mut input = PageAlignedArray::new(width * 2).unwrap();
+        fill_by_random(input.as_mut_slice());
+        let mut output = PageAlignedArray::new(width * 2).unwrap();
+        let mut tmp = PageAlignedArray::new(BLOCK_SIZE_PER_ITER).unwrap();
+
+        let inputs = input.split(2);
+        let mut outputs = output.split_mut(2);
+        let mut tmps = tmp.split_mut(1);
+
+        run_slices(
+            &program,
+            BLOCK_SIZE_PER_ITER,
+            Kernel::detect(),
+            &inputs,
+            &mut outputs,
+            &mut tmps,
+        )
+        .unwrap();
+        for i in 0..width {
+            assert_eq!(outputs[0][i], inputs[0][i] ^ inputs[1][i]);
+        }
+        assert!(outputs[1][..] == inputs[1][..]);
+
+        // missing tmp
+        assert_eq!(
+            run_slices(
+                &program,
+                BLOCK_SIZE_PER_ITER,
+                Kernel::detect(),
+                &inputs,
+                &mut outputs,
+                &mut []
+            ),
+            Err(RunError::PebbleOutOfRange(to_pos(4, 0)))
+        );
+
+        // writing to an input
+        let bad = vec![(to_pos(0, 1), vec![to_pos(1, 1)])];
+        assert_eq!(
+            run_slices(
+                &bad,
+                BLOCK_SIZE_PER_ITER,
+                Kernel::detect(),
+                &inputs,
+                &mut outputs,
+                &mut tmps
+            ),
+            Err(RunError::WriteToInput(to_pos(0, 1)))
+        );
+
+        // different lengths
+        assert_eq!(
+            run_slices(
+                &program,
+                BLOCK_SIZE_PER_ITER,
+                Kernel::detect(),
+                &[inputs[0], &inputs[1][32..]],
+                &mut outputs,
+                &mut tmps
+            ),
+            Err(RunError::LengthMismatch)
+        );
+
+        // too small tmp
+        assert_eq!(
+            run_slices(
+                &program,
+                BLOCK_SIZE_PER_ITER,
+                Kernel::detect(),
+                &inputs,
+                &mut outputs,
+                &mut [&mut tmps[0][..32]]
+            ),
+            Err(RunError::InvalidTmp)
+        );
+    }
+
+    #[test]
+    fn run_slices_tail_test() {
+        let kernel = Kernel::detect();
+        check(2, 3, 0, BLOCK_SIZE_PER_ITER, kernel);
+        check(
+            2,
+            BLOCK_SIZE_PER_ITER * 2 + 37,
+            0,
+            BLOCK_SIZE_PER_ITER,
+            kernel,
+        );
+        check(2, BLOCK_SIZE_PER_ITER + 5, 1, BLOCK_SIZE_PER_ITER, kernel);
+        check(2, BLOCK_SIZE_PER_ITER * 3, 7, BLOCK_SIZE_PER_ITER, kernel);
+
+        // the staging area is allocated by try_new
+        assert!(matches!(
+            PageAlignedArray::try_new(usize::MAX),
+            Err(RunError::OutOfMemory(usize::MAX))
+        ));
+    }
+
+    #[test]
+    fn run_slices_parallel_test() {
+        let n = 5;
+        let program: Vec<(Pos, Vec<Pos>)> = vec![
+            (to_pos(n + 2, 0), (0..n).map(|i| to_pos(i, 1)).collect()),
+            (to_pos(n, 1), vec![to_pos(n + 2, 0), to_pos(1, 1)]),
+            (to_pos(n + 1, 1), vec![to_pos(n + 2, 0)]),
+        ];
+
+        for width in [100, 64 * 7, 64 * 7 + 100] {
+            let mut input = PageAlignedArray::new(width * n).unwrap();
+            fill_by_random(input.as_mut_slice());
+            let inputs = input.split(n);
+
+            let expected: Vec<u8> = (0..width)
+                .map(|i| inputs.iter().fold(0, |acc, s| acc ^ s[i]))
+                .collect();
+
+            for nr_threads in [0, 1, 2, 3, 8, 16] {
+                let mut output = PageAlignedArray::new(width * 2).unwrap();
+                let mut outputs = output.split_mut(2);
+                run_slices_parallel(
+                    &program,
+                    64,
+                    Kernel::detect(),
+                    1,
+                    nr_threads,
+                    &inputs,
+                    &mut outputs,
+                )
+                .unwrap();
+
+                for i in 0..width {
+                    assert_eq!(outputs[0][i], expected[i] ^ inputs[1][i]);
+                }
+                assert!(outputs[1][..] == expected[..]);
+            }
+        }
+
+        // the tmp area of a worker can not be allocated
+        let input = PageAlignedArray::new(64 * 7 * n).unwrap();
+        let mut output = PageAlignedArray::new(64 * 7 * 2).unwrap();
+        assert_eq!(
+            run_slices_parallel(
+                &program,
+                64,
+                Kernel::detect(),
+                usize::MAX,
+                2,
+                &input.split(n),
+                &mut output.split_mut(2),
+            ),
+            Err(RunError::OutOfMemory(usize::MAX))
+        );
+    }
+
+    #[test]
+    fn check_program_test() {
+        // out0 = in0 ^ in1; out1 = out0 ^ in1 (= in0)
+        let code = vec![
+            (to_pos(2, 1), vec![to_pos(0, 1), to_pos(1, 1)]),
+            (to_pos(3, 1), vec![to_pos(2, 1), to_pos(1, 1)]),
+        ];
+        let program = Program {
+            nr_inputs: 2,
+            nr_outputs: 2,
+            nr_tmps: 0,
+            block_size: 64,
+            kernel: Kernel::detect(),
+            nr_threads: 1,
+            nr_planes: 8,
+            code,
+        };
+        let check = CheckProgram::new(&program);
+        // out0 is read, so only out1 is tested by a fused step
+        assert_eq!(check.checks.iter().filter(|c| c.is_some()).count(), 2);
+        assert_eq!(check.code.len(), 3);
+
+        let width = 64 * 3 + 8;
+        let zero = vec![0u8; width];
+        let mut random = vec![0u8; width];
+        fill_by_random(&mut random);
+        let mut last = vec![0u8; width];
+        last[width - 1] = 1;
+
+        assert_eq!(check.execute(&[&zero, &zero]), Ok(vec![]));
+        assert_eq!(check.execute(&[&zero, &random]), Ok(vec![0]));
+        assert_eq!(check.execute(&[&last, &last]), Ok(vec![1]));
+        assert_eq!(check.execute(&[&random, &zero]), Ok(vec![0, 1]));
+        assert_eq!(
+            check.execute(&[&zero]),
+            Err(RunError::WrongNumberOfInputs {
+                expected: 2,
+                actual: 1
+            })
+        );
+    }
+
+    #[test]
+    fn block_size_test() {
+        for kernel in [Kernel::Avx2, Kernel::Sse2, Kernel::Portable] {
+            if !kernel.is_available() {
+                continue;
+            }
+            for block_size in [64, 192, 1024, 4096] {
+                for n in [2, 5, 15, 17] {
+                    check(n, block_size * 3, 0, block_size, kernel);
+                    check(n, block_size * 2 + 100, 0, block_size, kernel);
+                }
+            }
+        }
+
+        let mut tmp = PageAlignedArray::new(4096).unwrap();
+        assert_eq!(
+            run_slices(
+                &sample_program(),
+                96,
+                Kernel::detect(),
+                &[],
+                &mut [],
+                &mut tmp.split_mut(1)
+            ),
+            Err(RunError::InvalidBlockSize(96))
+        );
     }
 }
@@ -0,0 +1,233 @@
+use crate::error::XorSlpError;
+use crate::*;
+use std::convert::TryFrom;
+
+/*
+ * Every stripe of a shard file is a record: a header followed by the payload.
+ * The header is HEADER_LEN bytes of little endian integers:
+ *
+ *   magic "XSLS", version: u16, generator kind: u8, reserved: u8,
+ *   nr_data_block: u16, nr_parity_block: u16, shard index: u16, reserved: u16,
+ *   stripe index: u64, object length: u64, shard chunk: u32, payload length: u32,
+ *   CRC32C of the payload: u32, CRC32C of the preceding 44 bytes: u32
+ *
+ * The shard chunk is the block size of the stripe layout, i.e., the payload length of full stripes.
+ */
+const MAGIC: &[u8; 4] = b"XSLS";
+pub const SHARD_VERSION: u16 = 1;
+pub const HEADER_LEN: usize = 48;
+
+lazy_static! {
+    // the reflected Castagnoli polynomial
+    static ref CRC32C_TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            let mut crc = i as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 { (crc >> 1) ^ 0x82f6_3b78 } else { crc >> 1 };
+            }
+            *entry = crc;
+        }
+        table
+    };
+}
+
+pub fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for b in data {
+        crc = CRC32C_TABLE[((crc ^ *b as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeneratorKind {
+    IsaRsv, // vandermonde::isa_rsv
+    Rsv,    // vandermonde::rsv
+    Custom,
+}
+
+impl GeneratorKind {
+    fn to_u8(self) -> u8 {
+        match self {
+            GeneratorKind::IsaRsv => 0,
+            GeneratorKind::Rsv => 1,
+            GeneratorKind::Custom => 2,
+        }
+    }
+
+    fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(GeneratorKind::IsaRsv),
+            1 => Some(GeneratorKind::Rsv),
+            2 => Some(GeneratorKind::Custom),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShardHeader {
+    pub parameter: Parameter,
+    pub generator: GeneratorKind,
+    pub index: usize, // of the shard; data shards come first
+    pub stripe: u64,  // the position of the stripe in the object
+    pub object_len: u64,
+    pub shard_chunk: usize,
+    pub payload_len: usize,
+    pub checksum: u32, // CRC32C of the payload
+}
+
+impl ShardHeader {
+    /// Serialize the header; `InvalidParameter` if a field does not fit its width.
+    pub fn to_bytes(&self) -> Result<[u8; HEADER_LEN], XorSlpError> {
+        fn narrow<T: TryFrom<usize>>(value: usize, field: &str) -> Result<T, XorSlpError> {
+            T::try_from(value).map_err(|_| {
+                XorSlpError::InvalidParameter(format!(
+                    "{} {} does not fit the header",
+                    field, value
+                ))
+            })
+        }
+        let nr_data_block: u16 = narrow(self.parameter.nr_data_block, "nr_data_block")?;
+        let nr_parity_block: u16 = narrow(self.parameter.nr_parity_block, "nr_parity_block")?;
+        let index: u16 = narrow(self.index, "index")?;
+        let shard_chunk: u32 = narrow(self.shard_chunk, "shard_chunk")?;
+        let payload_len: u32 = narrow(self.payload_len, "payload_len")?;
+
+        let mut bytes = [0u8; HEADER_LEN];
+        bytes[0..4].copy_from_slice(MAGIC);
+        bytes[4..6].copy_from_slice(&SHARD_VERSION.to_le_bytes());
+        bytes[6] = self.generator.to_u8();
+        bytes[8..10].copy_from_slice(&nr_data_block.to_le_bytes());
+        bytes[10..12].copy_from_slice(&nr_parity_block.to_le_bytes());
+        bytes[12..14].copy_from_slice(&index.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.stripe.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.object_len.to_le_bytes());
+        bytes[32..36].copy_from_slice(&shard_chunk.to_le_bytes());
+        bytes[36..40].copy_from_slice(&payload_len.to_le_bytes());
+        bytes[40..44].copy_from_slice(&self.checksum.to_le_bytes());
+        let crc = crc32c(&bytes[..44]);
+        bytes[44..48].copy_from_slice(&crc.to_le_bytes());
+        Ok(bytes)
+    }
+
+    /// Parse a header, checking its magic, version and own checksum.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, XorSlpError> {
+        let malformed = |msg: &str| Err(XorSlpError::MalformedInput(msg.to_owned()));
+        if bytes.len() < HEADER_LEN || &bytes[0..4] != MAGIC {
+            return malformed("not a shard header");
+        }
+        if crc32c(&bytes[..44]) != u32_at(bytes, 44) {
+            return malformed("the shard header is corrupted");
+        }
+        if u16_at(bytes, 4) != SHARD_VERSION {
+            return malformed("the shard version is not supported");
+        }
+        let generator = match GeneratorKind::from_u8(bytes[6]) {
+            Some(g) => g,
+            None => return malformed("unknown generator matrix"),
+        };
+
+        Ok(ShardHeader {
+            parameter: Parameter {
+                nr_data_block: u16_at(bytes, 8) as usize,
+                nr_parity_block: u16_at(bytes, 10) as usize,
+            },
+            generator,
+            index: u16_at(bytes, 12) as usize,
+            stripe: u64_at(bytes, 16),
+            object_len: u64_at(bytes, 24),
+            shard_chunk: u32_at(bytes, 32) as usize,
+            payload_len: u32_at(bytes, 36) as usize,
+            checksum: u32_at(bytes, 40),
+        })
+    }
+
+    /// Whether `payload` is the one this header was written for
+    pub fn matches(&self, payload: &[u8]) -> bool {
+        payload.len() == self.payload_len && crc32c(payload) == self.checksum
+    }
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    let mut b = [0u8; 4];
+    b.copy_from_slice(&bytes[at..at + 4]);
+    u32::from_le_bytes(b)
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    let mut b = [0u8; 8];
+    b.copy_from_slice(&bytes[at..at + 8]);
+    u64::from_le_bytes(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_test() {
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+        assert_eq!(crc32c(b""), 0);
+
+        let payload = b"some payload";
+        let header = ShardHeader {
+            parameter: Parameter {
+                nr_data_block: 10,
+                nr_parity_block: 4,
+            },
+            generator: GeneratorKind::IsaRsv,
+            index: 12,
+            stripe: 3,
+            object_len: 1 << 40,
+            shard_chunk: 1 << 20,
+            payload_len: payload.len(),
+            checksum: crc32c(payload),
+        };
+        let bytes = header.to_bytes().unwrap();
+        assert_eq!(ShardHeader::from_bytes(&bytes), Ok(header));
+        assert!(header.matches(payload));
+        assert!(!header.matches(b"some paylaod"));
+
+        // any flipped bit is detected
+        for i in 0..HEADER_LEN {
+            let mut broken = bytes;
+            broken[i] ^= 0x10;
+            assert!(ShardHeader::from_bytes(&broken).is_err(), "{}", i);
+        }
+        assert!(ShardHeader::from_bytes(&bytes[..HEADER_LEN - 1]).is_err());
+
+        // a field which does not fit is not truncated
+        for broken in [
+            ShardHeader {
+                index: 1 << 16,
+                ..header
+            },
+            ShardHeader {
+                parameter: Parameter {
+                    nr_data_block: 1 << 16,
+                    nr_parity_block: 4,
+                },
+                ..header
+            },
+            ShardHeader {
+                shard_chunk: 1 << 32,
+                ..header
+            },
+            ShardHeader {
+                payload_len: 1 << 32,
+                ..header
+            },
+        ] {
+            assert!(matches!(
+                broken.to_bytes(),
+                Err(XorSlpError::InvalidParameter(_))
+            ));
+        }
+    }
+}
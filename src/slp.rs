@@ -1,4 +1,5 @@
 use crate::bitmatrix::*;
+use crate::error::XorSlpError;
 use crate::*;
 use std::fs::File;
 use std::ops::{Index, IndexMut};
@@ -167,11 +168,10 @@ impl SLP {
         }
     }
 
-    pub fn build_from_file(file: File) -> Option<Self> {
-        let repr = BitMatrix::build_from_file(file);
-        let repr = repr.as_ref()?;
+    pub fn build_from_file(file: File) -> Result<Self, XorSlpError> {
+        let repr = BitMatrix::build_from_file(file)?;
 
-        Some(SLP::build_from_bitmatrix_not_depending_variables(&repr))
+        Ok(SLP::build_from_bitmatrix_not_depending_variables(&repr))
     }
 
     pub fn num_of_original_constants(&self) -> usize {
@@ -222,7 +222,8 @@ impl SLP {
         }
     }
 
-    pub fn to_trivial_graph(&self) -> Graph {
+    /// `self` is assumed to be shrinked, i.e., every variable depends on at least two constants.
+    pub fn to_trivial_graph(&self) -> Result<Graph, XorSlpError> {
         let mut graph = Graph::new();
 
         for i in 0..self.height() {
@@ -234,8 +235,11 @@ impl SLP {
                 .filter_map(|(idx, b)| if *b { Some(idx) } else { None })
                 .collect();
 
-            if positions.len() == 1 {
-                unreachable!("unexpected");
+            if positions.len() < 2 {
+                return Err(XorSlpError::MalformedInput(format!(
+                    "variable {} depends on less than two constants",
+                    i
+                )));
             } else {
                 graph.push((
                     Term::Var(i),
@@ -248,6 +252,6 @@ impl SLP {
             }
         }
 
-        graph
+        Ok(graph)
     }
 }
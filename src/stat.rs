@@ -1,6 +1,7 @@
 use crate::reorder::{self, Pebble};
 use std::collections::BTreeSet;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Stat {
     pub nr_xors: usize,
     pub nr_memacc: usize,
@@ -74,7 +75,7 @@ pub fn analyze(program: &Vec<(Pebble, Vec<Pebble>)>) -> Stat {
 
     let mut cap = 1;
     loop {
-        if check_runnable(&program, cap) {
+        if check_runnable(program, cap) {
             stat.required_cache_capacity = cap;
             return stat;
         }
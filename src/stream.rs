@@ -0,0 +1,224 @@
+use crate::encoder::Encoder;
+use crate::error::XorSlpError;
+use crate::run::PageAlignedArray;
+use std::io::{ErrorKind, Read, Write};
+
+/*
+ * An object is encoded stripe by stripe:
+ * each stripe consists of `nr_data_block * shard_chunk` bytes of the object,
+ * and its data and parity shards are appended to the sinks.
+ * The last stripe is shorter and its shards are `Encoder::shard_len(rest)` bytes.
+ * Hence every stripe can be decoded independently.
+ */
+pub struct EncodeStream<'a> {
+    encoder: &'a Encoder,
+    shard_chunk: usize,
+    data: PageAlignedArray,
+    parity: PageAlignedArray,
+}
+
+impl<'a> EncodeStream<'a> {
+    /// `shard_chunk` is the number of bytes each shard receives per stripe, which is a positive multiple of 8.
+    /// Multiples of `8 * block_size` run fastest.
+    ///
+    /// The stream holds `(nr_data_block + nr_parity_block) * shard_chunk` bytes at most.
+    pub fn new(encoder: &'a Encoder, shard_chunk: usize) -> Result<Self, XorSlpError> {
+        if shard_chunk == 0 || !shard_chunk.is_multiple_of(8) {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "shard chunk {} is not a positive multiple of 8",
+                shard_chunk
+            )));
+        }
+
+        let parameter = encoder.parameter();
+        let data = PageAlignedArray::try_new(parameter.nr_data_block.saturating_mul(shard_chunk))?;
+        let parity =
+            PageAlignedArray::try_new(parameter.nr_parity_block.saturating_mul(shard_chunk))?;
+
+        Ok(EncodeStream {
+            encoder,
+            shard_chunk,
+            data,
+            parity,
+        })
+    }
+
+    pub fn shard_chunk(&self) -> usize {
+        self.shard_chunk
+    }
+
+    /// Encode everything `reader` yields, and write the data shards to `sinks[..nr_data_block]`
+    /// and the parity shards to the rest. Returns the number of bytes read.
+    pub fn encode<R: Read, W: Write>(
+        &mut self,
+        reader: &mut R,
+        sinks: &mut [W],
+    ) -> Result<u64, XorSlpError> {
+        let parameter = self.encoder.parameter();
+        let nr_shards = parameter.nr_data_block + parameter.nr_parity_block;
+
+        if sinks.len() != nr_shards {
+            return Err(XorSlpError::InvalidParameter(format!(
+                "{} sinks for {} shards",
+                sinks.len(),
+                nr_shards
+            )));
+        }
+
+        let total = self.encode_stripes(reader, |shards| {
+            for (sink, shard) in sinks.iter_mut().zip(shards) {
+                sink.write_all(shard)?;
+            }
+            Ok(())
+        })?;
+
+        for sink in sinks.iter_mut() {
+            sink.flush()?;
+        }
+        Ok(total)
+    }
+
+    /// Encode everything `reader` yields, and pass the data shards followed by the parity shards
+    /// of each stripe to `f`, in order. Returns the number of bytes read.
+    ///
+    /// An empty object is a single stripe of empty shards.
+    pub fn encode_stripes<R, F>(&mut self, reader: &mut R, mut f: F) -> Result<u64, XorSlpError>
+    where
+        R: Read,
+        F: FnMut(&[&[u8]]) -> Result<(), XorSlpError>,
+    {
+        let parameter = self.encoder.parameter();
+        let nr_data = parameter.nr_data_block;
+        let nr_parity = parameter.nr_parity_block;
+
+        let mut total = 0;
+        loop {
+            let filled = fill(reader, self.data.as_mut_slice())?;
+            if filled == 0 {
+                if total == 0 {
+                    f(&vec![&[][..]; nr_data + nr_parity])?;
+                }
+                break;
+            }
+            total += filled as u64;
+
+            let len = self.encoder.shard_len(filled);
+            self.data.as_mut_slice()[filled..nr_data * len].fill(0);
+
+            let data_shards: Vec<&[u8]> =
+                self.data.as_slice()[..nr_data * len].chunks(len).collect();
+            let mut parity_shards: Vec<&mut [u8]> = self.parity.as_mut_slice()[..nr_parity * len]
+                .chunks_mut(len)
+                .collect();
+            self.encoder.encode(&data_shards, &mut parity_shards)?;
+
+            let shards: Vec<&[u8]> = data_shards
+                .iter()
+                .cloned()
+                .chain(parity_shards.iter().map(|s| &s[..]))
+                .collect();
+            f(&shards)?;
+
+            if filled < self.data.as_slice().len() {
+                break;
+            }
+        }
+
+        Ok(total)
+    }
+}
+
+// read until `buf` is full or `reader` reaches the end
+fn fill<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::Options;
+    use crate::run::RunError;
+    use crate::*;
+
+    // yields at most 7 bytes per read
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = std::cmp::min(std::cmp::min(buf.len(), 7), self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn encode_stream_test() {
+        let parameter = Parameter {
+            nr_data_block: 4,
+            nr_parity_block: 2,
+        };
+        let encoder = Encoder::new(parameter, Options::default()).unwrap();
+        let shard_chunk = 8 * 64 * 3;
+        let mut stream = EncodeStream::new(&encoder, shard_chunk).unwrap();
+
+        for object_len in [0, 100, 4 * shard_chunk, 4 * shard_chunk * 2 + 1234] {
+            let mut object = vec![0u8; object_len];
+            fill_by_random(&mut object);
+
+            let mut sinks = vec![Vec::new(); 6];
+            let read = stream.encode(&mut Trickle(&object), &mut sinks).unwrap();
+            assert_eq!(read, object_len as u64);
+
+            // encode each stripe separately
+            let mut expected = vec![Vec::new(); 6];
+            for stripe in object.chunks(4 * shard_chunk) {
+                let len = encoder.shard_len(stripe.len());
+                let mut data = stripe.to_vec();
+                data.resize(4 * len, 0);
+                let mut parity = vec![0u8; 2 * len];
+
+                let data_shards: Vec<&[u8]> = data.chunks(len).collect();
+                let mut parity_shards: Vec<&mut [u8]> = parity.chunks_mut(len).collect();
+                encoder.encode(&data_shards, &mut parity_shards).unwrap();
+
+                for (e, s) in expected
+                    .iter_mut()
+                    .zip(data.chunks(len).chain(parity.chunks(len)))
+                {
+                    e.extend_from_slice(s);
+                }
+            }
+            assert!(sinks == expected);
+        }
+
+        let mut nr_stripes = 0;
+        stream
+            .encode_stripes(&mut &[][..], |shards| {
+                assert!(shards.len() == 6 && shards.iter().all(|s| s.is_empty()));
+                nr_stripes += 1;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(nr_stripes, 1);
+
+        assert!(matches!(
+            stream.encode(&mut &[0u8; 10][..], &mut vec![Vec::new(); 5]),
+            Err(XorSlpError::InvalidParameter(_))
+        ));
+        assert!(EncodeStream::new(&encoder, 12).is_err());
+        assert!(matches!(
+            EncodeStream::new(&encoder, usize::MAX - 7),
+            Err(XorSlpError::BufferMismatch(RunError::OutOfMemory(_)))
+        ));
+    }
+}
@@ -307,10 +307,10 @@ mod test {
 
     #[test]
     fn test_iszero() {
-        assert_eq!(PGF2::zero().is_zero(), true);
-        assert_eq!(zero1().is_zero(), true);
-        assert_eq!(p1().is_zero(), false);
-        assert_eq!(p2().is_zero(), false);
+        assert!(PGF2::zero().is_zero());
+        assert!(zero1().is_zero());
+        assert!(!p1().is_zero());
+        assert!(!p2().is_zero());
     }
 
     #[test]
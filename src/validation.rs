@@ -63,7 +63,7 @@ pub fn term_computation_to_valuation(computation: &[(Term, Vec<Term>)]) -> Valua
     for (t, children) in computation {
         let mut val = BTreeSet::new();
         for c in children {
-            let v = get_val(&valuation, &c);
+            let v = get_val(&valuation, c);
             val = xor_set(&val, &v);
         }
         valuation.insert(t.clone(), val);
@@ -90,22 +90,28 @@ pub fn modified_systematic_vandermonde<F: Field>(size: MatrixSize, v: &[F]) -> O
 }
 
 pub fn rsv(data_fragments: usize, parity_fragments: usize) -> Matrix<GF_2_8> {
+    rsv_over(data_fragments, parity_fragments).unwrap()
+}
+
+/// `rsv` over any field with a primitive element \alpha, e.g., `gf2w::GF_2_4`.
+/// `None` if \alpha^1, ..., \alpha^{data + parity} are not distinct from each other and from 1.
+pub fn rsv_over<F: Field + HasPrimitiveElement>(
+    data_fragments: usize,
+    parity_fragments: usize,
+) -> Option<Matrix<F>> {
     let height = data_fragments + parity_fragments;
 
-    let velems: Vec<GF_2_8> = (1..=height)
-        .map(|i| GF_2_8::PRIMITIVE_ELEMENT.exp(i as u32))
+    let velems: Vec<F> = (1..=height)
+        .map(|i| F::PRIMITIVE_ELEMENT.exp(i as u32))
         .collect();
 
-    let m: Matrix<GF_2_8> = modified_systematic_vandermonde(
+    modified_systematic_vandermonde(
         MatrixSize {
             height,
             width: data_fragments,
         },
         &velems,
     )
-    .unwrap();
-
-    m
 }
 
 pub fn nonsystematic_rsv(data_fragments: usize, parity_fragments: usize) -> Matrix<GF_2_8> {
@@ -158,13 +164,12 @@ pub fn isa_rsv(data: usize, parity: usize) -> Matrix<GF_2_8> {
 mod tests {
     use super::*;
     use itertools::Itertools;
-    use rand::prelude::*;
 
     #[test]
     fn test_rsv1() {
         let m = rsv(10, 4);
 
-        let remove_pattern = (0..14).into_iter().combinations(4);
+        let remove_pattern = (0..14).combinations(4);
 
         for remove in remove_pattern.into_iter() {
             let mut tmp = m.clone();
@@ -182,7 +187,7 @@ mod tests {
     fn test_isa_rsv1() {
         let m = isa_rsv(10, 4);
 
-        let remove_pattern = (0..14).into_iter().combinations(4);
+        let remove_pattern = (0..14).combinations(4);
 
         for remove in remove_pattern.into_iter() {
             let mut tmp = m.clone();
@@ -240,7 +245,7 @@ mod tests {
                 height: 4,
                 width: 4,
             },
-            &vec![r.exp(1), r.exp(2), r.exp(3), r.exp(4)],
+            &[r.exp(1), r.exp(2), r.exp(3), r.exp(4)],
         )
         .unwrap();
 
@@ -252,7 +257,7 @@ mod tests {
                 height: 4,
                 width: 4,
             },
-            &vec![r.exp(2), r.exp(2), r.exp(3), r.exp(4)],
+            &[r.exp(2), r.exp(2), r.exp(3), r.exp(4)],
         );
 
         assert!(v2.is_none());
@@ -262,7 +267,7 @@ mod tests {
                 height: 5,
                 width: 4,
             },
-            &vec![r.exp(1), r.exp(2), r.exp(3), r.exp(4), r.exp(5)],
+            &[r.exp(1), r.exp(2), r.exp(3), r.exp(4), r.exp(5)],
         )
         .unwrap();
 
@@ -284,7 +289,7 @@ mod tests {
                 height: 5,
                 width: 4,
             },
-            &vec![r.exp(1), r.exp(2), r.exp(3), r.exp(4), r.exp(5)],
+            &[r.exp(1), r.exp(2), r.exp(3), r.exp(4), r.exp(5)],
         )
         .unwrap();
 
@@ -302,7 +307,7 @@ mod tests {
                 height: 5,
                 width: 4,
             },
-            &vec![r.exp(1), r.exp(2), r.exp(3), r.exp(4), r.exp(5)],
+            &[r.exp(1), r.exp(2), r.exp(3), r.exp(4), r.exp(5)],
         )
         .unwrap();
 
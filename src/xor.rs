@@ -1,25 +1,24 @@
 #![allow(clippy::missing_safety_doc)]
 #![allow(clippy::too_many_arguments)]
 
-use crate::BLOCK_SIZE_PER_ITER;
 use std::arch::x86_64::*;
 
-pub unsafe fn page_generic_slow(dst: *mut u8, vs: &[*const u8]) {
+pub unsafe fn page_generic_slow(dst: *mut u8, vs: &[*const u8], len: usize) {
     // we take this implementation to deal the case dst in vs
     if vs.contains(&(dst as *const u8)) {
         for ptr in vs {
-            if dst as *const u8 == *ptr {
+            if std::ptr::eq(dst, *ptr) {
                 continue;
             } else {
-                for i in 0..BLOCK_SIZE_PER_ITER {
+                for i in 0..len {
                     *dst.add(i) ^= *ptr.add(i);
                 }
             }
         }
     } else {
-        std::ptr::copy_nonoverlapping(vs[0], dst, BLOCK_SIZE_PER_ITER);
+        std::ptr::copy_nonoverlapping(vs[0], dst, len);
         for ptr in vs.iter().skip(1) {
-            for i in 0..BLOCK_SIZE_PER_ITER {
+            for i in 0..len {
                 *dst.add(i) ^= *ptr.add(i);
             }
         }
@@ -27,11 +26,11 @@ pub unsafe fn page_generic_slow(dst: *mut u8, vs: &[*const u8]) {
 }
 
 #[target_feature(enable = "avx2")]
-pub unsafe fn avx2_page_generic(dst: *mut u8, vs: &[*const u8]) {
+pub unsafe fn avx2_page_generic(dst: *mut u8, vs: &[*const u8], len: usize) {
     let mut dst: *mut __m256i = dst as *mut __m256i;
     let mut v0: *const __m256i = vs[0] as *const __m256i;
 
-    for cur in 0..(BLOCK_SIZE_PER_ITER / 128) {
+    for cur in 0..(len / 128) {
         let mut reg0 = _mm256_load_si256(v0);
         let mut reg1 = _mm256_load_si256(v0.add(1));
         let mut reg2 = _mm256_load_si256(v0.add(2));
@@ -62,12 +61,12 @@ pub unsafe fn avx2_page_generic(dst: *mut u8, vs: &[*const u8]) {
 }
 
 #[target_feature(enable = "avx2")]
-pub unsafe fn avx2_page_xor2(dst: *mut u8, v1: *const u8, v2: *const u8) {
+pub unsafe fn avx2_page_xor2(dst: *mut u8, v1: *const u8, v2: *const u8, len: usize) {
     let mut dst: *mut __m256i = dst as *mut __m256i;
     let mut v1: *const __m256i = v1 as *const __m256i;
     let mut v2: *const __m256i = v2 as *const __m256i;
 
-    for _ in 0..(BLOCK_SIZE_PER_ITER / 128) {
+    for _ in 0..(len / 128) {
         let reg0 = _mm256_load_si256(v1);
         let reg1 = _mm256_load_si256(v1.add(1));
         let reg2 = _mm256_load_si256(v1.add(2));
@@ -94,13 +93,19 @@ pub unsafe fn avx2_page_xor2(dst: *mut u8, v1: *const u8, v2: *const u8) {
 }
 
 #[target_feature(enable = "avx2")]
-pub unsafe fn avx2_page_xor3(dst: *mut u8, v1: *const u8, v2: *const u8, v3: *const u8) {
+pub unsafe fn avx2_page_xor3(
+    dst: *mut u8,
+    v1: *const u8,
+    v2: *const u8,
+    v3: *const u8,
+    len: usize,
+) {
     let mut dst: *mut __m256i = dst as *mut __m256i;
     let mut v1: *const __m256i = v1 as *const __m256i;
     let mut v2: *const __m256i = v2 as *const __m256i;
     let mut v3: *const __m256i = v3 as *const __m256i;
 
-    for _ in 0..(BLOCK_SIZE_PER_ITER / 128) {
+    for _ in 0..(len / 128) {
         let reg0 = _mm256_load_si256(v1);
         let reg1 = _mm256_load_si256(v1.add(1));
         let reg2 = _mm256_load_si256(v1.add(2));
@@ -144,6 +149,7 @@ pub unsafe fn avx2_page_xor4(
     v2: *const u8,
     v3: *const u8,
     v4: *const u8,
+    len: usize,
 ) {
     let mut dst: *mut __m256i = dst as *mut __m256i;
     let mut v1: *const __m256i = v1 as *const __m256i;
@@ -151,7 +157,7 @@ pub unsafe fn avx2_page_xor4(
     let mut v3: *const __m256i = v3 as *const __m256i;
     let mut v4: *const __m256i = v4 as *const __m256i;
 
-    for _ in 0..(BLOCK_SIZE_PER_ITER / 128) {
+    for _ in 0..(len / 128) {
         let reg0 = _mm256_load_si256(v1);
         let reg1 = _mm256_load_si256(v1.add(1));
         let reg2 = _mm256_load_si256(v1.add(2));
@@ -207,6 +213,7 @@ pub unsafe fn avx2_page_xor5(
     v3: *const u8,
     v4: *const u8,
     v5: *const u8,
+    len: usize,
 ) {
     let mut dst: *mut __m256i = dst as *mut __m256i;
     let mut v1: *const __m256i = v1 as *const __m256i;
@@ -215,7 +222,7 @@ pub unsafe fn avx2_page_xor5(
     let mut v4: *const __m256i = v4 as *const __m256i;
     let mut v5: *const __m256i = v5 as *const __m256i;
 
-    for _ in 0..(BLOCK_SIZE_PER_ITER / 128) {
+    for _ in 0..(len / 128) {
         let reg0 = _mm256_load_si256(v1);
         let reg1 = _mm256_load_si256(v1.add(1));
         let reg2 = _mm256_load_si256(v1.add(2));
@@ -283,6 +290,7 @@ pub unsafe fn avx2_page_xor6(
     v4: *const u8,
     v5: *const u8,
     v6: *const u8,
+    len: usize,
 ) {
     let mut dst: *mut __m256i = dst as *mut __m256i;
     let mut v1: *const __m256i = v1 as *const __m256i;
@@ -292,7 +300,7 @@ pub unsafe fn avx2_page_xor6(
     let mut v5: *const __m256i = v5 as *const __m256i;
     let mut v6: *const __m256i = v6 as *const __m256i;
 
-    for _ in 0..(BLOCK_SIZE_PER_ITER / 128) {
+    for _ in 0..(len / 128) {
         let reg0 = _mm256_load_si256(v1);
         let reg1 = _mm256_load_si256(v1.add(1));
         let reg2 = _mm256_load_si256(v1.add(2));
@@ -372,6 +380,7 @@ pub unsafe fn avx2_page_xor7(
     v5: *const u8,
     v6: *const u8,
     v7: *const u8,
+    len: usize,
 ) {
     let mut dst: *mut __m256i = dst as *mut __m256i;
     let mut v1: *const __m256i = v1 as *const __m256i;
@@ -382,7 +391,7 @@ pub unsafe fn avx2_page_xor7(
     let mut v6: *const __m256i = v6 as *const __m256i;
     let mut v7: *const __m256i = v7 as *const __m256i;
 
-    for _ in 0..(BLOCK_SIZE_PER_ITER / 128) {
+    for _ in 0..(len / 128) {
         let reg0 = _mm256_load_si256(v1);
         let reg1 = _mm256_load_si256(v1.add(1));
         let reg2 = _mm256_load_si256(v1.add(2));
@@ -474,6 +483,7 @@ pub unsafe fn avx2_page_xor8(
     v6: *const u8,
     v7: *const u8,
     v8: *const u8,
+    len: usize,
 ) {
     let mut dst: *mut __m256i = dst as *mut __m256i;
     let mut v1: *const __m256i = v1 as *const __m256i;
@@ -485,7 +495,7 @@ pub unsafe fn avx2_page_xor8(
     let mut v7: *const __m256i = v7 as *const __m256i;
     let mut v8: *const __m256i = v8 as *const __m256i;
 
-    for _ in 0..(BLOCK_SIZE_PER_ITER / 128) {
+    for _ in 0..(len / 128) {
         let reg0 = _mm256_load_si256(v1);
         let reg1 = _mm256_load_si256(v1.add(1));
         let reg2 = _mm256_load_si256(v1.add(2));
@@ -589,6 +599,7 @@ pub unsafe fn avx2_page_xor9(
     v7: *const u8,
     v8: *const u8,
     v9: *const u8,
+    len: usize,
 ) {
     let mut dst: *mut __m256i = dst as *mut __m256i;
     let mut v1: *const __m256i = v1 as *const __m256i;
@@ -601,7 +612,7 @@ pub unsafe fn avx2_page_xor9(
     let mut v8: *const __m256i = v8 as *const __m256i;
     let mut v9: *const __m256i = v9 as *const __m256i;
 
-    for _ in 0..(BLOCK_SIZE_PER_ITER / 128) {
+    for _ in 0..(len / 128) {
         let reg0 = _mm256_load_si256(v1);
         let reg1 = _mm256_load_si256(v1.add(1));
         let reg2 = _mm256_load_si256(v1.add(2));
@@ -717,6 +728,7 @@ pub unsafe fn avx2_page_xor10(
     v8: *const u8,
     v9: *const u8,
     v10: *const u8,
+    len: usize,
 ) {
     let mut dst: *mut __m256i = dst as *mut __m256i;
     let mut v1: *const __m256i = v1 as *const __m256i;
@@ -730,7 +742,7 @@ pub unsafe fn avx2_page_xor10(
     let mut v9: *const __m256i = v9 as *const __m256i;
     let mut v10: *const __m256i = v10 as *const __m256i;
 
-    for _ in 0..(BLOCK_SIZE_PER_ITER / 128) {
+    for _ in 0..(len / 128) {
         let reg0 = _mm256_load_si256(v1);
         let reg1 = _mm256_load_si256(v1.add(1));
         let reg2 = _mm256_load_si256(v1.add(2));
@@ -858,6 +870,7 @@ pub unsafe fn avx2_page_xor11(
     v9: *const u8,
     v10: *const u8,
     v11: *const u8,
+    len: usize,
 ) {
     let mut dst: *mut __m256i = dst as *mut __m256i;
     let mut v1: *const __m256i = v1 as *const __m256i;
@@ -872,7 +885,7 @@ pub unsafe fn avx2_page_xor11(
     let mut v10: *const __m256i = v10 as *const __m256i;
     let mut v11: *const __m256i = v11 as *const __m256i;
 
-    for _ in 0..(BLOCK_SIZE_PER_ITER / 128) {
+    for _ in 0..(len / 128) {
         let reg0 = _mm256_load_si256(v1);
         let reg1 = _mm256_load_si256(v1.add(1));
         let reg2 = _mm256_load_si256(v1.add(2));
@@ -1012,6 +1025,7 @@ pub unsafe fn avx2_page_xor12(
     v10: *const u8,
     v11: *const u8,
     v12: *const u8,
+    len: usize,
 ) {
     let mut dst: *mut __m256i = dst as *mut __m256i;
     let mut v1: *const __m256i = v1 as *const __m256i;
@@ -1027,7 +1041,7 @@ pub unsafe fn avx2_page_xor12(
     let mut v11: *const __m256i = v11 as *const __m256i;
     let mut v12: *const __m256i = v12 as *const __m256i;
 
-    for _ in 0..(BLOCK_SIZE_PER_ITER / 128) {
+    for _ in 0..(len / 128) {
         let reg0 = _mm256_load_si256(v1);
         let reg1 = _mm256_load_si256(v1.add(1));
         let reg2 = _mm256_load_si256(v1.add(2));
@@ -1179,6 +1193,7 @@ pub unsafe fn avx2_page_xor13(
     v11: *const u8,
     v12: *const u8,
     v13: *const u8,
+    len: usize,
 ) {
     let mut dst: *mut __m256i = dst as *mut __m256i;
     let mut v1: *const __m256i = v1 as *const __m256i;
@@ -1195,7 +1210,7 @@ pub unsafe fn avx2_page_xor13(
     let mut v12: *const __m256i = v12 as *const __m256i;
     let mut v13: *const __m256i = v13 as *const __m256i;
 
-    for _ in 0..(BLOCK_SIZE_PER_ITER / 128) {
+    for _ in 0..(len / 128) {
         let reg0 = _mm256_load_si256(v1);
         let reg1 = _mm256_load_si256(v1.add(1));
         let reg2 = _mm256_load_si256(v1.add(2));
@@ -1359,6 +1374,7 @@ pub unsafe fn avx2_page_xor14(
     v12: *const u8,
     v13: *const u8,
     v14: *const u8,
+    len: usize,
 ) {
     let mut dst: *mut __m256i = dst as *mut __m256i;
     let mut v1: *const __m256i = v1 as *const __m256i;
@@ -1376,7 +1392,7 @@ pub unsafe fn avx2_page_xor14(
     let mut v13: *const __m256i = v13 as *const __m256i;
     let mut v14: *const __m256i = v14 as *const __m256i;
 
-    for _ in 0..(BLOCK_SIZE_PER_ITER / 128) {
+    for _ in 0..(len / 128) {
         let reg0 = _mm256_load_si256(v1);
         let reg1 = _mm256_load_si256(v1.add(1));
         let reg2 = _mm256_load_si256(v1.add(2));
@@ -1552,6 +1568,7 @@ pub unsafe fn avx2_page_xor15(
     v13: *const u8,
     v14: *const u8,
     v15: *const u8,
+    len: usize,
 ) {
     let mut dst: *mut __m256i = dst as *mut __m256i;
     let mut v1: *const __m256i = v1 as *const __m256i;
@@ -1570,7 +1587,7 @@ pub unsafe fn avx2_page_xor15(
     let mut v14: *const __m256i = v14 as *const __m256i;
     let mut v15: *const __m256i = v15 as *const __m256i;
 
-    for _ in 0..(BLOCK_SIZE_PER_ITER / 128) {
+    for _ in 0..(len / 128) {
         let reg0 = _mm256_load_si256(v1);
         let reg1 = _mm256_load_si256(v1.add(1));
         let reg2 = _mm256_load_si256(v1.add(2));
@@ -1739,10 +1756,77 @@ pub unsafe fn avx2_page_xor15(
     }
 }
 
+/// `dst = vs[0] ^ vs[1] ^ ...` for `len` bytes, where `len` is a multiple of 128.
+pub unsafe fn avx2_page_xor(dst: *mut u8, vs: &[*const u8], len: usize) {
+    match vs.len() {
+        2 => avx2_page_xor2(dst, vs[0], vs[1], len),
+        3 => avx2_page_xor3(dst, vs[0], vs[1], vs[2], len),
+        4 => avx2_page_xor4(dst, vs[0], vs[1], vs[2], vs[3], len),
+        5 => avx2_page_xor5(dst, vs[0], vs[1], vs[2], vs[3], vs[4], len),
+        6 => avx2_page_xor6(dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], len),
+        7 => avx2_page_xor7(dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], len),
+        8 => avx2_page_xor8(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], len,
+        ),
+        9 => avx2_page_xor9(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], len,
+        ),
+        10 => avx2_page_xor10(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], len,
+        ),
+        11 => avx2_page_xor11(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10], len,
+        ),
+        12 => avx2_page_xor12(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11], len,
+        ),
+        13 => avx2_page_xor13(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11], vs[12], len,
+        ),
+        14 => avx2_page_xor14(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11], vs[12], vs[13], len,
+        ),
+        15 => avx2_page_xor15(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11], vs[12], vs[13], vs[14], len,
+        ),
+        _ => avx2_page_generic(dst, vs, len),
+    }
+}
+
+/// Whether `vs[0] ^ vs[1] ^ ...` is zero for `len` bytes, where `len` is a multiple of 64.
+/// Nothing is stored.
+#[target_feature(enable = "avx2")]
+pub unsafe fn avx2_page_xor_is_zero(vs: &[*const u8], len: usize) -> bool {
+    let mut acc0 = _mm256_setzero_si256();
+    let mut acc1 = _mm256_setzero_si256();
+
+    for cur in 0..(len / 64) {
+        let v0 = vs[0] as *const __m256i;
+        let mut reg0 = _mm256_load_si256(v0.add(2 * cur));
+        let mut reg1 = _mm256_load_si256(v0.add(2 * cur + 1));
+
+        for ptr in vs.iter().skip(1) {
+            let w = *ptr as *const __m256i;
+            reg0 = _mm256_xor_si256(reg0, _mm256_load_si256(w.add(2 * cur)));
+            reg1 = _mm256_xor_si256(reg1, _mm256_load_si256(w.add(2 * cur + 1)));
+        }
+        acc0 = _mm256_or_si256(acc0, reg0);
+        acc1 = _mm256_or_si256(acc1, reg1);
+    }
+
+    let acc = _mm256_or_si256(acc0, acc1);
+    _mm256_testz_si256(acc, acc) == 1
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::gen_data;
+    use crate::BLOCK_SIZE_PER_ITER;
 
     fn naiive_xor(dst: &mut [u8], left: &[u8], right: &[u8]) {
         assert!(dst.len() == BLOCK_SIZE_PER_ITER);
@@ -1755,11 +1839,14 @@ mod test {
     }
 
     fn gen_array(len: usize) -> Vec<u8> {
+        // keep misaligned buffers alive so that the allocator hands out a fresh address
+        let mut misaligned = Vec::new();
         loop {
             let v = vec![0u8; len];
-            if v.as_ptr() as usize % 32 == 0 {
+            if (v.as_ptr() as usize).is_multiple_of(32) {
                 return v;
             }
+            misaligned.push(v);
         }
     }
 
@@ -1773,12 +1860,21 @@ mod test {
 
         let mut dst2 = gen_array(BLOCK_SIZE_PER_ITER);
         unsafe {
-            avx2_page_xor2(dst2.as_mut_ptr(), left.as_ptr(), right.as_ptr());
+            avx2_page_xor2(
+                dst2.as_mut_ptr(),
+                left.as_ptr(),
+                right.as_ptr(),
+                BLOCK_SIZE_PER_ITER,
+            );
         }
 
         let mut dst3 = vec![0; BLOCK_SIZE_PER_ITER];
         unsafe {
-            page_generic_slow(dst3.as_mut_ptr(), &vec![left.as_ptr(), right.as_ptr()]);
+            page_generic_slow(
+                dst3.as_mut_ptr(),
+                &[left.as_ptr(), right.as_ptr()],
+                BLOCK_SIZE_PER_ITER,
+            );
         }
         assert!(dst1 == dst3);
 
@@ -1798,14 +1894,21 @@ mod test {
 
         let mut dst = gen_array(BLOCK_SIZE_PER_ITER);
         unsafe {
-            avx2_page_xor3(dst.as_mut_ptr(), v1.as_ptr(), v2.as_ptr(), v3.as_ptr());
+            avx2_page_xor3(
+                dst.as_mut_ptr(),
+                v1.as_ptr(),
+                v2.as_ptr(),
+                v3.as_ptr(),
+                BLOCK_SIZE_PER_ITER,
+            );
         }
 
         let mut dst_ = vec![0; BLOCK_SIZE_PER_ITER];
         unsafe {
             page_generic_slow(
                 dst_.as_mut_ptr(),
-                &vec![v1.as_ptr(), v2.as_ptr(), v3.as_ptr()],
+                &[v1.as_ptr(), v2.as_ptr(), v3.as_ptr()],
+                BLOCK_SIZE_PER_ITER,
             );
         }
         assert!(dst == dst_);
@@ -1835,6 +1938,7 @@ mod test {
                 v2.as_ptr(),
                 v3.as_ptr(),
                 v4.as_ptr(),
+                BLOCK_SIZE_PER_ITER,
             );
         }
 
@@ -1842,7 +1946,8 @@ mod test {
         unsafe {
             page_generic_slow(
                 dst_.as_mut_ptr(),
-                &vec![v1.as_ptr(), v2.as_ptr(), v3.as_ptr(), v4.as_ptr()],
+                &[v1.as_ptr(), v2.as_ptr(), v3.as_ptr(), v4.as_ptr()],
+                BLOCK_SIZE_PER_ITER,
             );
         }
         assert!(dst == dst_);
@@ -1876,6 +1981,7 @@ mod test {
                 v3.as_ptr(),
                 v4.as_ptr(),
                 v5.as_ptr(),
+                BLOCK_SIZE_PER_ITER,
             );
         }
 
@@ -1912,6 +2018,7 @@ mod test {
                 v4.as_ptr(),
                 v5.as_ptr(),
                 v6.as_ptr(),
+                BLOCK_SIZE_PER_ITER,
             );
         }
 
@@ -1952,6 +2059,7 @@ mod test {
                 v5.as_ptr(),
                 v6.as_ptr(),
                 v7.as_ptr(),
+                BLOCK_SIZE_PER_ITER,
             );
         }
 
@@ -1996,6 +2104,7 @@ mod test {
                 v6.as_ptr(),
                 v7.as_ptr(),
                 v8.as_ptr(),
+                BLOCK_SIZE_PER_ITER,
             );
         }
 
@@ -2003,7 +2112,7 @@ mod test {
         unsafe {
             page_generic_slow(
                 dst_.as_mut_ptr(),
-                &vec![
+                &[
                     v1.as_ptr(),
                     v2.as_ptr(),
                     v3.as_ptr(),
@@ -2013,6 +2122,7 @@ mod test {
                     v7.as_ptr(),
                     v8.as_ptr(),
                 ],
+                BLOCK_SIZE_PER_ITER,
             );
         }
         assert!(dst_ == dst);
@@ -2062,6 +2172,7 @@ mod test {
                 v7.as_ptr(),
                 v8.as_ptr(),
                 v9.as_ptr(),
+                BLOCK_SIZE_PER_ITER,
             );
         }
 
@@ -2114,6 +2225,7 @@ mod test {
                 v8.as_ptr(),
                 v9.as_ptr(),
                 v10.as_ptr(),
+                BLOCK_SIZE_PER_ITER,
             );
         }
 
@@ -2170,6 +2282,7 @@ mod test {
                 v9.as_ptr(),
                 v10.as_ptr(),
                 v11.as_ptr(),
+                BLOCK_SIZE_PER_ITER,
             );
         }
 
@@ -2230,6 +2343,7 @@ mod test {
                 v10.as_ptr(),
                 v11.as_ptr(),
                 v12.as_ptr(),
+                BLOCK_SIZE_PER_ITER,
             );
         }
 
@@ -2294,6 +2408,7 @@ mod test {
                 v11.as_ptr(),
                 v12.as_ptr(),
                 v13.as_ptr(),
+                BLOCK_SIZE_PER_ITER,
             );
         }
 
@@ -2362,6 +2477,7 @@ mod test {
                 v12.as_ptr(),
                 v13.as_ptr(),
                 v14.as_ptr(),
+                BLOCK_SIZE_PER_ITER,
             );
         }
 
@@ -2434,6 +2550,7 @@ mod test {
                 v13.as_ptr(),
                 v14.as_ptr(),
                 v15.as_ptr(),
+                BLOCK_SIZE_PER_ITER,
             );
         }
     }
@@ -2475,6 +2592,7 @@ mod test {
                 v13.as_ptr(),
                 v14.as_ptr(),
                 v15.as_ptr(),
+                BLOCK_SIZE_PER_ITER,
             );
         }
         let mut dst2 = gen_array(BLOCK_SIZE_PER_ITER);
@@ -2498,6 +2616,7 @@ mod test {
                     v14.as_ptr(),
                     v15.as_ptr(),
                 ],
+                BLOCK_SIZE_PER_ITER,
             );
         }
 
@@ -2547,6 +2666,7 @@ mod test {
                     v16.as_ptr(),
                     v17.as_ptr(),
                 ],
+                BLOCK_SIZE_PER_ITER,
             );
         }
         let mut dst2 = vec![0; BLOCK_SIZE_PER_ITER];
@@ -2572,6 +2692,7 @@ mod test {
                     v16.as_ptr(),
                     v17.as_ptr(),
                 ],
+                BLOCK_SIZE_PER_ITER,
             );
         }
 
@@ -1207,11 +1207,52 @@ pub unsafe fn avx2_page_xor15(
     }
 }
 
-#[cfg(all(test, feature = "64block"))]
+/// `dst = vs[0] ^ vs[1] ^ ...` for 64 bytes.
+pub unsafe fn avx2_page_xor(dst: *mut u8, vs: &[*const u8]) {
+    match vs.len() {
+        2 => avx2_page_xor2(dst, vs[0], vs[1]),
+        3 => avx2_page_xor3(dst, vs[0], vs[1], vs[2]),
+        4 => avx2_page_xor4(dst, vs[0], vs[1], vs[2], vs[3]),
+        5 => avx2_page_xor5(dst, vs[0], vs[1], vs[2], vs[3], vs[4]),
+        6 => avx2_page_xor6(dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5]),
+        7 => avx2_page_xor7(dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6]),
+        8 => avx2_page_xor8(dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7]),
+        9 => avx2_page_xor9(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8],
+        ),
+        10 => avx2_page_xor10(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9],
+        ),
+        11 => avx2_page_xor11(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+        ),
+        12 => avx2_page_xor12(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11],
+        ),
+        13 => avx2_page_xor13(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11], vs[12],
+        ),
+        14 => avx2_page_xor14(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11], vs[12], vs[13],
+        ),
+        15 => avx2_page_xor15(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11], vs[12], vs[13], vs[14],
+        ),
+        _ => avx2_page_generic(dst, vs),
+    }
+}
+
+#[cfg(test)]
 mod test {
     use super::*;
     use crate::gen_data;
-    use crate::BLOCK_SIZE_PER_ITER;
+
+    // the kernels of this module process 64 bytes
+    const BLOCK_SIZE_PER_ITER: usize = 64;
 
     fn naiive_xor(dst: &mut [u8], left: &[u8], right: &[u8]) {
         assert!(dst.len() == BLOCK_SIZE_PER_ITER);
@@ -1224,11 +1265,14 @@ mod test {
     }
 
     fn gen_array(len: usize) -> Vec<u8> {
+        // keep misaligned buffers alive so that the allocator hands out a fresh address
+        let mut misaligned = Vec::new();
         loop {
             let v = vec![0u8; len];
-            if v.as_ptr() as usize % 32 == 0 {
+            if (v.as_ptr() as usize).is_multiple_of(32) {
                 return v;
             }
+            misaligned.push(v);
         }
     }
 
@@ -0,0 +1,180 @@
+#![allow(clippy::missing_safety_doc)]
+#![allow(clippy::too_many_arguments)]
+
+// The same kernels as `xor` on 64-bit words, which run on any target.
+// The pointers need no alignment, and `len` is a multiple of 8.
+
+macro_rules! portable_page_xor {
+    ( $name:ident, $v0:ident $(, $v:ident)+ ) => {
+        pub unsafe fn $name(dst: *mut u8, $v0: *const u8, $($v: *const u8,)+ len: usize) {
+            let dst = dst as *mut u64;
+            let $v0 = $v0 as *const u64;
+            $( let $v = $v as *const u64; )+
+
+            for i in 0..(len / 8) {
+                let mut w = $v0.add(i).read_unaligned();
+                $( w ^= $v.add(i).read_unaligned(); )+
+                dst.add(i).write_unaligned(w);
+            }
+        }
+    };
+}
+
+pub unsafe fn portable_page_generic(dst: *mut u8, vs: &[*const u8], len: usize) {
+    let dst = dst as *mut u64;
+
+    for i in 0..(len / 8) {
+        let mut w = (vs[0] as *const u64).add(i).read_unaligned();
+        for ptr in vs.iter().skip(1) {
+            w ^= (*ptr as *const u64).add(i).read_unaligned();
+        }
+        dst.add(i).write_unaligned(w);
+    }
+}
+
+portable_page_xor!(portable_page_xor2, v1, v2);
+portable_page_xor!(portable_page_xor3, v1, v2, v3);
+portable_page_xor!(portable_page_xor4, v1, v2, v3, v4);
+portable_page_xor!(portable_page_xor5, v1, v2, v3, v4, v5);
+portable_page_xor!(portable_page_xor6, v1, v2, v3, v4, v5, v6);
+portable_page_xor!(portable_page_xor7, v1, v2, v3, v4, v5, v6, v7);
+portable_page_xor!(portable_page_xor8, v1, v2, v3, v4, v5, v6, v7, v8);
+portable_page_xor!(portable_page_xor9, v1, v2, v3, v4, v5, v6, v7, v8, v9);
+portable_page_xor!(portable_page_xor10, v1, v2, v3, v4, v5, v6, v7, v8, v9, v10);
+portable_page_xor!(
+    portable_page_xor11,
+    v1,
+    v2,
+    v3,
+    v4,
+    v5,
+    v6,
+    v7,
+    v8,
+    v9,
+    v10,
+    v11
+);
+portable_page_xor!(
+    portable_page_xor12,
+    v1,
+    v2,
+    v3,
+    v4,
+    v5,
+    v6,
+    v7,
+    v8,
+    v9,
+    v10,
+    v11,
+    v12
+);
+portable_page_xor!(
+    portable_page_xor13,
+    v1,
+    v2,
+    v3,
+    v4,
+    v5,
+    v6,
+    v7,
+    v8,
+    v9,
+    v10,
+    v11,
+    v12,
+    v13
+);
+portable_page_xor!(
+    portable_page_xor14,
+    v1,
+    v2,
+    v3,
+    v4,
+    v5,
+    v6,
+    v7,
+    v8,
+    v9,
+    v10,
+    v11,
+    v12,
+    v13,
+    v14
+);
+portable_page_xor!(
+    portable_page_xor15,
+    v1,
+    v2,
+    v3,
+    v4,
+    v5,
+    v6,
+    v7,
+    v8,
+    v9,
+    v10,
+    v11,
+    v12,
+    v13,
+    v14,
+    v15
+);
+
+/// `dst = vs[0] ^ vs[1] ^ ...` for `len` bytes, where `len` is a multiple of 8.
+pub unsafe fn portable_page_xor(dst: *mut u8, vs: &[*const u8], len: usize) {
+    match vs.len() {
+        2 => portable_page_xor2(dst, vs[0], vs[1], len),
+        3 => portable_page_xor3(dst, vs[0], vs[1], vs[2], len),
+        4 => portable_page_xor4(dst, vs[0], vs[1], vs[2], vs[3], len),
+        5 => portable_page_xor5(dst, vs[0], vs[1], vs[2], vs[3], vs[4], len),
+        6 => portable_page_xor6(dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], len),
+        7 => portable_page_xor7(dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], len),
+        8 => portable_page_xor8(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], len,
+        ),
+        9 => portable_page_xor9(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], len,
+        ),
+        10 => portable_page_xor10(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], len,
+        ),
+        11 => portable_page_xor11(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10], len,
+        ),
+        12 => portable_page_xor12(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11], len,
+        ),
+        13 => portable_page_xor13(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11], vs[12], len,
+        ),
+        14 => portable_page_xor14(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11], vs[12], vs[13], len,
+        ),
+        15 => portable_page_xor15(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11], vs[12], vs[13], vs[14], len,
+        ),
+        _ => portable_page_generic(dst, vs, len),
+    }
+}
+
+/// Whether `vs[0] ^ vs[1] ^ ...` is zero for `len` bytes, where `len` is a multiple of 8.
+/// Nothing is stored.
+pub unsafe fn portable_page_xor_is_zero(vs: &[*const u8], len: usize) -> bool {
+    let mut acc = 0;
+
+    for i in 0..(len / 8) {
+        let mut w = (vs[0] as *const u64).add(i).read_unaligned();
+        for ptr in vs.iter().skip(1) {
+            w ^= (*ptr as *const u64).add(i).read_unaligned();
+        }
+        acc |= w;
+    }
+
+    acc == 0
+}
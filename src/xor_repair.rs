@@ -48,7 +48,7 @@ fn build_syntax(
 
         for var in gen_iter(0, valuation.height()) {
             let value = &valuation[var];
-            let count = bitvec_distance(&value, &rest);
+            let count = bitvec_distance(value, &rest);
 
             // first found is preferred
             if count < current_min {
@@ -88,7 +88,7 @@ fn xor_pair_finder(
     for i in 0..slp.num_of_variables() {
         // this is one of remained goal
         let goal = &slp[i];
-        let (depends, _) = build_syntax(valuation, &goal, gen_iter);
+        let (depends, _) = build_syntax(valuation, goal, gen_iter);
 
         if popcount(&depends) < popcount(&program[i]) {
             program[i] = depends;
@@ -97,7 +97,7 @@ fn xor_pair_finder(
 
     for i in 0..program.width() {
         for j in i + 1..program.width() {
-            let count = count_occurences2(i, j, &program);
+            let count = count_occurences2(i, j, program);
 
             match count_max.cmp(&count) {
                 Ordering::Equal => {
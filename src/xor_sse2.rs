@@ -0,0 +1,223 @@
+#![allow(clippy::missing_safety_doc)]
+#![allow(clippy::too_many_arguments)]
+
+// The same kernels as `xor` for CPUs without AVX2.
+// Every pointer must be 16-byte aligned, and `len` is a multiple of 64.
+
+use std::arch::x86_64::*;
+
+macro_rules! sse2_page_xor {
+    ( $name:ident, $v0:ident $(, $v:ident)+ ) => {
+        #[target_feature(enable = "sse2")]
+        pub unsafe fn $name(dst: *mut u8, $v0: *const u8, $($v: *const u8,)+ len: usize) {
+            let dst = dst as *mut __m128i;
+            let $v0 = $v0 as *const __m128i;
+            $( let $v = $v as *const __m128i; )+
+
+            for cur in 0..(len / 64) {
+                let i = 4 * cur;
+                let mut reg0 = _mm_load_si128($v0.add(i));
+                let mut reg1 = _mm_load_si128($v0.add(i + 1));
+                let mut reg2 = _mm_load_si128($v0.add(i + 2));
+                let mut reg3 = _mm_load_si128($v0.add(i + 3));
+                $(
+                    reg0 = _mm_xor_si128(reg0, _mm_load_si128($v.add(i)));
+                    reg1 = _mm_xor_si128(reg1, _mm_load_si128($v.add(i + 1)));
+                    reg2 = _mm_xor_si128(reg2, _mm_load_si128($v.add(i + 2)));
+                    reg3 = _mm_xor_si128(reg3, _mm_load_si128($v.add(i + 3)));
+                )+
+                _mm_store_si128(dst.add(i), reg0);
+                _mm_store_si128(dst.add(i + 1), reg1);
+                _mm_store_si128(dst.add(i + 2), reg2);
+                _mm_store_si128(dst.add(i + 3), reg3);
+            }
+        }
+    };
+}
+
+#[target_feature(enable = "sse2")]
+pub unsafe fn sse2_page_generic(dst: *mut u8, vs: &[*const u8], len: usize) {
+    let dst = dst as *mut __m128i;
+
+    for cur in 0..(len / 64) {
+        let i = 4 * cur;
+        let v0 = vs[0] as *const __m128i;
+        let mut reg0 = _mm_load_si128(v0.add(i));
+        let mut reg1 = _mm_load_si128(v0.add(i + 1));
+        let mut reg2 = _mm_load_si128(v0.add(i + 2));
+        let mut reg3 = _mm_load_si128(v0.add(i + 3));
+
+        for ptr in vs.iter().skip(1) {
+            let w = *ptr as *const __m128i;
+            reg0 = _mm_xor_si128(reg0, _mm_load_si128(w.add(i)));
+            reg1 = _mm_xor_si128(reg1, _mm_load_si128(w.add(i + 1)));
+            reg2 = _mm_xor_si128(reg2, _mm_load_si128(w.add(i + 2)));
+            reg3 = _mm_xor_si128(reg3, _mm_load_si128(w.add(i + 3)));
+        }
+        _mm_store_si128(dst.add(i), reg0);
+        _mm_store_si128(dst.add(i + 1), reg1);
+        _mm_store_si128(dst.add(i + 2), reg2);
+        _mm_store_si128(dst.add(i + 3), reg3);
+    }
+}
+
+sse2_page_xor!(sse2_page_xor2, v1, v2);
+sse2_page_xor!(sse2_page_xor3, v1, v2, v3);
+sse2_page_xor!(sse2_page_xor4, v1, v2, v3, v4);
+sse2_page_xor!(sse2_page_xor5, v1, v2, v3, v4, v5);
+sse2_page_xor!(sse2_page_xor6, v1, v2, v3, v4, v5, v6);
+sse2_page_xor!(sse2_page_xor7, v1, v2, v3, v4, v5, v6, v7);
+sse2_page_xor!(sse2_page_xor8, v1, v2, v3, v4, v5, v6, v7, v8);
+sse2_page_xor!(sse2_page_xor9, v1, v2, v3, v4, v5, v6, v7, v8, v9);
+sse2_page_xor!(sse2_page_xor10, v1, v2, v3, v4, v5, v6, v7, v8, v9, v10);
+sse2_page_xor!(
+    sse2_page_xor11,
+    v1,
+    v2,
+    v3,
+    v4,
+    v5,
+    v6,
+    v7,
+    v8,
+    v9,
+    v10,
+    v11
+);
+sse2_page_xor!(
+    sse2_page_xor12,
+    v1,
+    v2,
+    v3,
+    v4,
+    v5,
+    v6,
+    v7,
+    v8,
+    v9,
+    v10,
+    v11,
+    v12
+);
+sse2_page_xor!(
+    sse2_page_xor13,
+    v1,
+    v2,
+    v3,
+    v4,
+    v5,
+    v6,
+    v7,
+    v8,
+    v9,
+    v10,
+    v11,
+    v12,
+    v13
+);
+sse2_page_xor!(
+    sse2_page_xor14,
+    v1,
+    v2,
+    v3,
+    v4,
+    v5,
+    v6,
+    v7,
+    v8,
+    v9,
+    v10,
+    v11,
+    v12,
+    v13,
+    v14
+);
+sse2_page_xor!(
+    sse2_page_xor15,
+    v1,
+    v2,
+    v3,
+    v4,
+    v5,
+    v6,
+    v7,
+    v8,
+    v9,
+    v10,
+    v11,
+    v12,
+    v13,
+    v14,
+    v15
+);
+
+/// `dst = vs[0] ^ vs[1] ^ ...` for `len` bytes, where `len` is a multiple of 64.
+pub unsafe fn sse2_page_xor(dst: *mut u8, vs: &[*const u8], len: usize) {
+    match vs.len() {
+        2 => sse2_page_xor2(dst, vs[0], vs[1], len),
+        3 => sse2_page_xor3(dst, vs[0], vs[1], vs[2], len),
+        4 => sse2_page_xor4(dst, vs[0], vs[1], vs[2], vs[3], len),
+        5 => sse2_page_xor5(dst, vs[0], vs[1], vs[2], vs[3], vs[4], len),
+        6 => sse2_page_xor6(dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], len),
+        7 => sse2_page_xor7(dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], len),
+        8 => sse2_page_xor8(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], len,
+        ),
+        9 => sse2_page_xor9(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], len,
+        ),
+        10 => sse2_page_xor10(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], len,
+        ),
+        11 => sse2_page_xor11(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10], len,
+        ),
+        12 => sse2_page_xor12(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11], len,
+        ),
+        13 => sse2_page_xor13(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11], vs[12], len,
+        ),
+        14 => sse2_page_xor14(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11], vs[12], vs[13], len,
+        ),
+        15 => sse2_page_xor15(
+            dst, vs[0], vs[1], vs[2], vs[3], vs[4], vs[5], vs[6], vs[7], vs[8], vs[9], vs[10],
+            vs[11], vs[12], vs[13], vs[14], len,
+        ),
+        _ => sse2_page_generic(dst, vs, len),
+    }
+}
+
+/// Whether `vs[0] ^ vs[1] ^ ...` is zero for `len` bytes, where `len` is a multiple of 64.
+/// Nothing is stored.
+#[target_feature(enable = "sse2")]
+pub unsafe fn sse2_page_xor_is_zero(vs: &[*const u8], len: usize) -> bool {
+    let mut acc = _mm_setzero_si128();
+
+    for cur in 0..(len / 64) {
+        let i = 4 * cur;
+        let v0 = vs[0] as *const __m128i;
+        let mut reg0 = _mm_load_si128(v0.add(i));
+        let mut reg1 = _mm_load_si128(v0.add(i + 1));
+        let mut reg2 = _mm_load_si128(v0.add(i + 2));
+        let mut reg3 = _mm_load_si128(v0.add(i + 3));
+
+        for ptr in vs.iter().skip(1) {
+            let w = *ptr as *const __m128i;
+            reg0 = _mm_xor_si128(reg0, _mm_load_si128(w.add(i)));
+            reg1 = _mm_xor_si128(reg1, _mm_load_si128(w.add(i + 1)));
+            reg2 = _mm_xor_si128(reg2, _mm_load_si128(w.add(i + 2)));
+            reg3 = _mm_xor_si128(reg3, _mm_load_si128(w.add(i + 3)));
+        }
+        acc = _mm_or_si128(
+            acc,
+            _mm_or_si128(_mm_or_si128(reg0, reg1), _mm_or_si128(reg2, reg3)),
+        );
+    }
+
+    _mm_movemask_epi8(_mm_cmpeq_epi8(acc, _mm_setzero_si128())) == 0xffff
+}